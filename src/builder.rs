@@ -0,0 +1,176 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    clients::transport::HttpTransport,
+    config::RunConfig,
+    events::ExportObserver,
+    model::{collection::Collection, movie::Movie},
+    persisters::{exporter::Exporter, letterboxd_csv_exporter::ChunkLimits},
+    report::ExportReport,
+    transformers::{deduplicator::OverlapPolicy, rating_transformer::RatingOptions},
+};
+
+/// Movies fetched from a collection of a profile.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FetchedCollection {
+    pub collection: Collection,
+    pub movies: Vec<Movie>,
+    /// Films listed on the profile whose pages couldn't be read.
+    pub failed: usize,
+    pub fetched_pages: usize,
+    /// Listing pages that couldn't be fetched, whose films are missing from `movies`.
+    pub failed_pages: usize,
+}
+
+/// Configures an export of a Filmow profile, then either writes its files with `run` or returns
+/// its movies with `fetch`.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), String> {
+/// use filmow2letterboxd::{Collection, ExportBuilder};
+///
+/// let fetched = ExportBuilder::new("cinefilo")
+///     .collections([Collection::Watched])
+///     .fetch()
+///     .await?;
+/// for movie in &fetched[0].movies {
+///     println!("{} ({})", movie.title, movie.year);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExportBuilder {
+    user: String,
+    config: RunConfig,
+}
+
+impl ExportBuilder {
+    pub fn new(user: impl Into<String>) -> Self {
+        ExportBuilder {
+            user: user.into(),
+            config: RunConfig::default(),
+        }
+    }
+
+    /// Starts from `config` instead of the default configuration.
+    pub fn with_config(mut self, config: RunConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Collections fetched, both by default.
+    pub fn collections(mut self, collections: impl IntoIterator<Item = Collection>) -> Self {
+        self.config.collections = collections.into_iter().collect();
+        self
+    }
+
+    /// Names of the exporters every collection is written with, `letterboxd-csv` by default.
+    pub fn exporters<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.config.exporters = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Makes `exporter` available and writes every collection with it too.
+    pub fn exporter(mut self, exporter: Arc<dyn Exporter>) -> Self {
+        self.config.exporters.push(exporter.name().to_string());
+        self.config.custom_exporters.push(exporter);
+        self
+    }
+
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.config.output_dir = output_dir.into();
+        self
+    }
+
+    pub fn rating(mut self, rating: RatingOptions) -> Self {
+        self.config.rating = rating;
+        self
+    }
+
+    pub fn overlap_policy(mut self, policy: OverlapPolicy) -> Self {
+        self.config.overlap_policy = policy;
+        self
+    }
+
+    pub fn csv_chunks(mut self, limits: ChunkLimits) -> Self {
+        self.config.csv_chunks = limits;
+        self
+    }
+
+    pub fn sort_output(mut self, sort: bool) -> Self {
+        self.config.sort_output = sort;
+        self
+    }
+
+    pub fn show_progress(mut self, show: bool) -> Self {
+        self.config.show_progress = show;
+        self
+    }
+
+    /// Leaves out films already in this Letterboxd data export.
+    pub fn letterboxd_export(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.letterboxd_export = Some(path.into());
+        self
+    }
+
+    /// Root every Filmow url is built from, https://filmow.com by default.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.config.base_url = base_url.into();
+        self
+    }
+
+    /// Transport every request goes through instead of the default reqwest one.
+    pub fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.config.transport = Some(transport);
+        self
+    }
+
+    /// Reads Filmow pages saved in `dir` instead of accessing the network.
+    pub fn offline_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.offline_dir = Some(dir.into());
+        self
+    }
+
+    /// Keeps a raw copy of every fetched page in `dir`.
+    pub fn archive_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.archive_dir = Some(dir.into());
+        self
+    }
+
+    pub fn record_cassette(mut self, cassette: impl Into<PathBuf>) -> Self {
+        self.config.record_cassette = Some(cassette.into());
+        self
+    }
+
+    pub fn replay_cassette(mut self, cassette: impl Into<PathBuf>) -> Self {
+        self.config.replay_cassette = Some(cassette.into());
+        self
+    }
+
+    pub fn selectors_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.config.selectors_file = Some(file.into());
+        self
+    }
+
+    /// Passes every page fetched, film read and file written to `observer` as the run goes.
+    pub fn observer(mut self, observer: Arc<dyn ExportObserver>) -> Self {
+        self.config.observers.push(observer);
+        self
+    }
+
+    pub fn config(&self) -> &RunConfig {
+        &self.config
+    }
+
+    /// Fetches the profile, writes the files of every exporter and reports how it went.
+    pub async fn run(self) -> Result<ExportReport, String> {
+        crate::run_with_config(self.user, self.config).await
+    }
+
+    /// Fetches the profile and returns the movies of every collection, without writing any file.
+    pub async fn fetch(self) -> Result<Vec<FetchedCollection>, String> {
+        crate::fetch_movies(self.user, self.config).await
+    }
+}
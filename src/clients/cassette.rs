@@ -0,0 +1,240 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::transport::{HttpResponse, HttpTransport};
+
+/// A request made to Filmow and the response it got, in the order they happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub url: String,
+    pub status: u16,
+    pub body: String,
+    /// Why the request failed without any response, such as a timeout. `status` and `body` are
+    /// left empty then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Interaction {
+    fn outcome(self) -> Result<HttpResponse, String> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(HttpResponse {
+                status: self.status,
+                body: self.body,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read cassette {}. Error: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse cassette {}. Error: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize cassette. Error: {}", e))?;
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write cassette {}. Error: {}", path.display(), e))
+    }
+}
+
+/// Transport that forwards requests to `inner` while recording every response, or the error a
+/// request failed with, in a cassette.
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn HttpTransport>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn HttpTransport>, path: &Path) -> Self {
+        RecordingTransport {
+            inner,
+            path: path.to_path_buf(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// Writes the interactions recorded so far to the cassette file.
+    pub fn save(&self) -> Result<PathBuf, String> {
+        self.cassette
+            .lock()
+            .expect("Cassette lock was poisoned")
+            .save(&self.path)?;
+        Ok(self.path.clone())
+    }
+}
+
+#[async_trait]
+impl HttpTransport for RecordingTransport {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        let response = self.inner.get(url).await;
+        let interaction = match &response {
+            Ok(response) => Interaction {
+                url: url.to_string(),
+                status: response.status,
+                body: response.body.clone(),
+                error: None,
+            },
+            Err(e) => Interaction {
+                url: url.to_string(),
+                status: 0,
+                body: String::new(),
+                error: Some(e.clone()),
+            },
+        };
+        self.cassette
+            .lock()
+            .expect("Cassette lock was poisoned")
+            .interactions
+            .push(interaction);
+        response
+    }
+}
+
+/// Transport serving the responses of a cassette instead of accessing the network.
+///
+/// Responses recorded for the same url are served in their original order, so retries see the
+/// same failures they saw while recording, including requests that failed without a response. The last response of a url is repeated once the
+/// others are used up.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    responses: Mutex<HashMap<String, VecDeque<Result<HttpResponse, String>>>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        let mut responses: HashMap<String, VecDeque<_>> = HashMap::new();
+        for interaction in cassette.interactions {
+            responses
+                .entry(interaction.url.clone())
+                .or_default()
+                .push_back(interaction.outcome());
+        }
+
+        ReplayTransport {
+            responses: Mutex::new(responses),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReplayTransport {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        let mut responses = self.responses.lock().expect("Cassette lock was poisoned");
+        match responses.get_mut(url) {
+            Some(url_responses) if url_responses.len() > 1 => {
+                url_responses.pop_front().expect("Checked length above")
+            }
+            Some(url_responses) => url_responses
+                .front()
+                .cloned()
+                .expect("Urls are only indexed with at least one response"),
+            None => {
+                log::error!("No response recorded in the cassette for url {}", url);
+                Ok(HttpResponse::not_found())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct UnreachableTransport;
+
+    #[async_trait]
+    impl HttpTransport for UnreachableTransport {
+        async fn get(&self, _url: &str) -> Result<HttpResponse, String> {
+            Err("Connection refused".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_requests_that_failed_while_recording_as_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let recorder = RecordingTransport::new(Arc::new(UnreachableTransport), &path);
+
+        let recorded = recorder.get("https://filmow.com/").await;
+        recorder.save().unwrap();
+        let replayed = ReplayTransport::load(&path)
+            .unwrap()
+            .get("https://filmow.com/")
+            .await;
+
+        assert_eq!(recorded.unwrap_err(), "Connection refused");
+        assert_eq!(replayed.unwrap_err(), "Connection refused");
+    }
+
+    #[tokio::test]
+    async fn replays_responses_of_a_url_in_order() {
+        let cassette = Cassette {
+            interactions: vec![
+                Interaction {
+                    url: "https://filmow.com/".to_string(),
+                    status: 0,
+                    body: String::new(),
+                    error: Some("Timed out".to_string()),
+                },
+                Interaction {
+                    url: "https://filmow.com/".to_string(),
+                    status: 200,
+                    body: "<html></html>".to_string(),
+                    error: None,
+                },
+            ],
+        };
+        let replay = ReplayTransport::new(cassette);
+
+        assert_eq!(
+            replay.get("https://filmow.com/").await.unwrap_err(),
+            "Timed out"
+        );
+        assert_eq!(
+            replay.get("https://filmow.com/").await.unwrap(),
+            HttpResponse::ok("<html></html>")
+        );
+        assert_eq!(
+            replay.get("https://filmow.com/").await.unwrap(),
+            HttpResponse::ok("<html></html>")
+        );
+    }
+
+    #[test]
+    fn loads_cassettes_recorded_without_errors() {
+        let cassette: Cassette = serde_json::from_str(
+            r#"{"interactions": [{"url": "https://filmow.com/", "status": 404, "body": ""}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            cassette.interactions[0].clone().outcome().unwrap(),
+            HttpResponse::not_found()
+        );
+    }
+}
@@ -1,9 +1,15 @@
 use crate::extractors::movie_extractor::MovieExtractor;
 
-use crate::model::movie::Movie;
+use crate::model::{collection::Collection, movie::Movie};
+use crate::progress::CollectionProgress;
+use crate::transformers::deduplicator::Deduplicator;
+use tokio::sync::{
+    mpsc::{self, Receiver, Sender},
+    Semaphore,
+};
 use tokio_retry::{
     strategy::{jitter, ExponentialBackoff},
-    Retry,
+    RetryIf,
 };
 
 use std::sync::Arc;
@@ -12,117 +18,262 @@ use crate::fetchers::{
     watched_list_fetcher::WatchedMoviesFetcher, watchlist_fetcher::WatchlistFetcher,
 };
 
-use reqwest::Client;
+use super::{filmow_urls::FilmowUrls, page_archive::PageArchive, transport::HttpTransport};
+
+/// Films discovered on listing pages waiting for their page to be fetched.
+const PRELIMINARY_INFO_BUFFER: usize = 64;
+/// Film pages fetched at the same time.
+const FILM_FETCH_CONCURRENCY: usize = 16;
 
 #[derive(Debug, Clone)]
 pub struct FilmowClient {
-    client: Client,
+    urls: FilmowUrls,
+    extractor: MovieExtractor,
+    transport: Arc<dyn HttpTransport>,
+    archive: Option<Arc<PageArchive>>,
 }
 
 impl FilmowClient {
-    pub fn new() -> Self {
+    pub fn with_transport(transport: Arc<dyn HttpTransport>) -> Self {
         Self {
-            client: Client::new(),
+            urls: FilmowUrls::default(),
+            extractor: MovieExtractor::default(),
+            transport,
+            archive: None,
         }
     }
 
-    pub async fn get_all_movies_from_watchlist(
-        shared_self: Arc<FilmowClient>,
-        user: Arc<String>,
-    ) -> Vec<Movie> {
-        let watchlist_fetcher = WatchlistFetcher::new(shared_self.clone());
-        WatchlistFetcher::get_all_movies_from_watchlist(Arc::new(watchlist_fetcher), user).await
+    pub fn with_urls(mut self, urls: FilmowUrls) -> Self {
+        self.urls = urls;
+        self
     }
 
-    pub async fn get_all_watched_movies(
+    /// Keeps a raw copy of every page fetched from Filmow in `archive`.
+    pub fn with_archive(mut self, archive: Arc<PageArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Sends every movie of `user`'s `collection` to `movies` as soon as its page is read.
+    /// Listing pages are read while film pages are still being fetched, and films wait
+    /// whenever the export falls behind. Fails when either stage crashed, leaving the movies
+    /// sent incomplete.
+    pub async fn stream_collection(
         shared_self: Arc<FilmowClient>,
+        collection: Collection,
         user: Arc<String>,
-    ) -> Vec<Movie> {
-        let watched_list_fetcher = WatchedMoviesFetcher::new(shared_self.clone());
-        WatchedMoviesFetcher::get_all_watched_movies(Arc::new(watched_list_fetcher), user).await
+        progress: Arc<CollectionProgress>,
+        deduplicator: Arc<Deduplicator>,
+        movies: Sender<Movie>,
+    ) -> Result<(), String> {
+        let (sender, receiver) = mpsc::channel(PRELIMINARY_INFO_BUFFER);
+        let discovery = match collection {
+            Collection::Watched => {
+                let fetcher = WatchedMoviesFetcher::new(shared_self.clone(), progress.clone());
+                tokio::spawn(WatchedMoviesFetcher::discover_watched_movies(
+                    Arc::new(fetcher),
+                    user,
+                    sender,
+                ))
+            }
+            Collection::Watchlist => {
+                let fetcher = WatchlistFetcher::new(shared_self.clone(), progress.clone());
+                tokio::spawn(WatchlistFetcher::discover_watchlist_movies(
+                    Arc::new(fetcher),
+                    user,
+                    sender,
+                ))
+            }
+        };
+
+        let resolved = FilmowClient::resolve_movies(
+            shared_self,
+            collection,
+            receiver,
+            progress,
+            deduplicator,
+            movies,
+        )
+        .await;
+        discovery
+            .await
+            .map_err(|e| format!("Reading the {} pages crashed: {}", collection, e))?;
+        resolved
     }
 
-    pub fn get_base_url() -> String {
-        "https://filmow.com".to_string()
+    pub fn with_extractor(mut self, extractor: MovieExtractor) -> Self {
+        self.extractor = extractor;
+        self
+    }
+
+    pub fn urls(&self) -> &FilmowUrls {
+        &self.urls
+    }
+
+    pub fn extractor(&self) -> &MovieExtractor {
+        &self.extractor
     }
 
     pub async fn get_html_from_url(&self, url: &str) -> Result<String, String> {
         let retry_strategy = ExponentialBackoff::from_millis(10).map(jitter).take(5);
-        Retry::spawn(retry_strategy, || async move {
-            self.get_html_from_url_no_retry(url).await
-        })
+        RetryIf::spawn(
+            retry_strategy,
+            || async move { self.get_html_from_url_no_retry(url).await },
+            |e: &FetchError| matches!(e, FetchError::Transient(_)),
+        )
         .await
+        .map_err(|e| match e {
+            FetchError::Permanent(e) | FetchError::Transient(e) => e,
+        })
     }
 
-    async fn get_html_from_url_no_retry(&self, url: &str) -> Result<String, String> {
-        match self.client.get(url).send().await {
-            Ok(resp) => {
-                if resp.status() == 404 {
-                    return Err("404 page not found".to_string());
-                }
-                match resp.text().await {
-                    Ok(text) => Ok(text),
-                    Err(e) => Err(format!(
-                        "Failed to get text form url {}. Error was {}",
-                        url, e
-                    )),
-                }
+    async fn get_html_from_url_no_retry(&self, url: &str) -> Result<String, FetchError> {
+        let resp = self
+            .transport
+            .get(url)
+            .await
+            .map_err(FetchError::Transient)?;
+
+        if resp.status == 404 {
+            self.archive_page(url, resp.status, None);
+            return Err(FetchError::Permanent("404 page not found".to_string()));
+        }
+        if !(200..300).contains(&resp.status) {
+            self.archive_page(url, resp.status, None);
+            let error = format!("Received status {} for url {}", resp.status, url);
+            // Filmow answers 429 when throttling us, which is worth waiting out.
+            return Err(if resp.status == 429 || resp.status >= 500 {
+                FetchError::Transient(error)
+            } else {
+                FetchError::Permanent(error)
+            });
+        }
+
+        self.archive_page(url, resp.status, Some(&resp.body));
+        Ok(resp.body)
+    }
+
+    fn archive_page(&self, url: &str, status: u16, body: Option<&str>) {
+        if let Some(archive) = &self.archive {
+            if let Err(e) = archive.record(url, status, body) {
+                log::error!("Could not archive page {}. Error: {}", url, e);
             }
-            Err(e) => Err(format!(
-                "Failed to get HTML for url: {}. Received error: {:?}",
-                url, e
-            )),
         }
     }
 
     async fn get_movie_from_url(&self, url: &str) -> Result<Movie, String> {
         match self.get_html_from_url(url).await {
-            Ok(html_body) => MovieExtractor::extract_movie_from_html(html_body.as_str(), url),
+            Ok(html_body) => self
+                .extractor
+                .extract_movie_from_html(html_body.as_str(), url),
             Err(e) => Err(e),
         }
     }
 
-    pub async fn parallel_build_movie_from_preliminary_info(
+    /// Builds the movie of every film received from `preliminary` that `deduplicator` admits,
+    /// fetching at most `FILM_FETCH_CONCURRENCY` film pages at a time, and sends the ones it
+    /// keeps to `movies`. Films are received as fast as they are listed, so the whole listing is
+    /// known early even when fetching their pages is slow.
+    async fn resolve_movies(
         shared_self: Arc<FilmowClient>,
-        info_vec: Vec<PreliminaryMovieInformation>,
-    ) -> Vec<Movie> {
+        collection: Collection,
+        mut preliminary: Receiver<PreliminaryMovieInformation>,
+        progress: Arc<CollectionProgress>,
+        deduplicator: Arc<Deduplicator>,
+        movies: Sender<Movie>,
+    ) -> Result<(), String> {
+        let permits = Arc::new(Semaphore::new(FILM_FETCH_CONCURRENCY));
         let mut children = vec![];
 
-        for info in info_vec {
+        while let Some(info) = preliminary.recv().await {
+            let canonical_url = shared_self.urls.canonical(&info.movie_url);
+            if !deduplicator.admit(collection, &canonical_url) {
+                progress.film_skipped();
+                continue;
+            }
+
+            let permits = permits.clone();
             let self_clone = shared_self.clone();
-            children.push(
-                tokio::spawn(async move {
-                log::info!("Fetching information for movie {}", info.movie_url);
-                match self_clone.get_movie_from_url(info.movie_url.as_str()).await {
-                        Ok(movie) => {
-                            log::info!("Successfully fetched information for Movie {}", movie.title);
-                            Some(Movie {
-                                title: movie.title,
-                                director: movie.director,
-                                year: movie.year,
-                                rating: info.rating
-                            })
-                        }
-                        Err(e) => {
-                            log::error!("Could not construct movie from url {}. Ignoring it and continuing. Error was: {}", info.movie_url, e);
-                            return None;
+            let progress = progress.clone();
+            let deduplicator = deduplicator.clone();
+            let movies = movies.clone();
+            children.push(tokio::spawn(async move {
+                let permit = permits
+                    .acquire_owned()
+                    .await
+                    .expect("Film fetch permits are never closed");
+                log::debug!("Fetching information for movie {}", info.movie_url);
+                let fetched = self_clone.get_movie_from_url(&info.movie_url).await;
+                drop(permit);
+                match fetched {
+                    Ok(fetched) => {
+                        if !deduplicator.resolved(collection, &canonical_url).await {
+                            progress.film_skipped();
+                            return;
                         }
+                        let movie = build_movie(fetched, info, &progress);
+                        // The receiver is only gone when the export was abandoned.
+                        let _ = movies.send(movie).await;
+                    }
+                    Err(e) => {
+                        progress.film_failed(&info.movie_url, &e);
+                        log::error!("Could not construct movie from url {}. Ignoring it and continuing. Error was: {}", info.movie_url, e);
+                        deduplicator.failed(collection, &canonical_url);
                     }
+                }
             }));
         }
+        deduplicator.listing_complete(collection);
 
-        let mut movies = vec![];
+        let mut crashed = None;
         for child in children {
-            let movie = child.await.expect("Could not join child thread");
-            movies.push(movie);
+            if let Err(e) = child.await {
+                crashed.get_or_insert(format!("Fetching a {} film crashed: {}", collection, e));
+            }
         }
-
-        movies.into_iter().flatten().collect()
+        deduplicator.resolution_complete(collection);
+        crashed.map_or(Ok(()), Err)
     }
 }
 
+/// Completes the movie read from a film page with the rating listed for it.
+fn build_movie(
+    movie: Movie,
+    info: PreliminaryMovieInformation,
+    progress: &CollectionProgress,
+) -> Movie {
+    log::debug!("Successfully fetched information for Movie {}", movie.title);
+    let rating = info.rating.unwrap_or_else(|e| {
+        log::error!(
+            "Could not parse rating for movie {} from url {}. Exporting it unrated. Error was: {}",
+            movie.title,
+            info.movie_url,
+            e
+        );
+        progress.rating_failed(&info.movie_url, &e);
+        None
+    });
+    let movie = Movie {
+        title: movie.title,
+        director: movie.director,
+        year: movie.year,
+        rating,
+    };
+    progress.film_resolved(&info.movie_url, &movie);
+    movie
+}
+
+/// Failure of a single request, telling apart the ones worth retrying.
+#[derive(Debug)]
+enum FetchError {
+    Permanent(String),
+    Transient(String),
+}
+
 #[derive(Debug)]
 pub struct PreliminaryMovieInformation {
     pub movie_url: String,
-    pub rating: Option<f32>,
+    /// Rating found on the listing page. Errors are kept so they can be reported once the
+    /// movie is built instead of being silently dropped.
+    pub rating: Result<Option<f32>, String>,
 }
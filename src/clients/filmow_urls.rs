@@ -0,0 +1,87 @@
+use crate::model::collection::Collection;
+
+pub const DEFAULT_BASE_URL: &str = "https://filmow.com";
+
+/// Builds every Filmow url the tool accesses from a single base url, so it can point to
+/// filmow.com, a local stand-in, a staging mirror or an archived copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilmowUrls {
+    base_url: String,
+}
+
+impl FilmowUrls {
+    pub fn new(base_url: &str) -> Result<Self, String> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        let host = base_url
+            .strip_prefix("https://")
+            .or_else(|| base_url.strip_prefix("http://"))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid Filmow base url '{}'. It must start with http:// or https://",
+                    base_url
+                )
+            })?;
+
+        if host.is_empty() || host.contains('?') || host.contains('#') {
+            return Err(format!(
+                "Invalid Filmow base url '{}'. Expected something like {}",
+                base_url, DEFAULT_BASE_URL
+            ));
+        }
+
+        Ok(FilmowUrls {
+            base_url: base_url.to_string(),
+        })
+    }
+
+    pub fn collection_page(&self, collection: Collection, user: &str, page: i32) -> String {
+        let collection_path = match collection {
+            Collection::Watched => "ja-vi",
+            Collection::Watchlist => "quero-ver",
+        };
+
+        if page == 1 {
+            return format!("{}/usuario/{}/{}/", self.base_url, user, collection_path);
+        }
+
+        format!(
+            "{}/usuario/{}/{}/?pagina={}",
+            self.base_url, user, collection_path, page
+        )
+    }
+
+    /// Turns an href found in a Filmow page into an absolute url.
+    pub fn resolve(&self, href: &str) -> String {
+        if href.starts_with("http://") || href.starts_with("https://") {
+            return href.to_string();
+        }
+
+        if let Some(without_slashes) = href.strip_prefix("//") {
+            let scheme = self.base_url.split("://").next().unwrap_or("https");
+            return format!("{}://{}", scheme, without_slashes);
+        }
+
+        if href.starts_with('/') {
+            return format!("{}{}", self.base_url, href);
+        }
+
+        format!("{}/{}", self.base_url, href)
+    }
+
+    /// Url identifying the film an href links to, whatever query, fragment or trailing slash
+    /// the link had.
+    pub fn canonical(&self, href: &str) -> String {
+        let url = self.resolve(href);
+        let without_fragment = url.split('#').next().unwrap_or_default();
+        let path = without_fragment.split('?').next().unwrap_or_default();
+        format!("{}/", path.trim_end_matches('/'))
+    }
+}
+
+impl Default for FilmowUrls {
+    fn default() -> Self {
+        FilmowUrls {
+            base_url: DEFAULT_BASE_URL.to_string(),
+        }
+    }
+}
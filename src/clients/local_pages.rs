@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use async_trait::async_trait;
+
+use super::{
+    page_archive::ArchiveIndex,
+    transport::{HttpResponse, HttpTransport},
+};
+use select::{
+    document::Document,
+    predicate::{Attr, Name, Predicate},
+};
+
+/// Previously saved Filmow pages read from a local directory instead of the network.
+///
+/// Pages are looked up by the layout described in `relative_path_for_url` or through the index
+/// of a `PageArchive`. Files saved with a browser's "save page as" can have any name: they are
+/// matched through the canonical URL the page declares about itself.
+#[derive(Debug, Clone)]
+pub struct LocalPageStore {
+    root: PathBuf,
+    saved_pages: HashMap<String, PathBuf>,
+}
+
+impl LocalPageStore {
+    pub fn open(root: &Path) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!(
+                "Offline pages directory {} does not exist",
+                root.display()
+            ));
+        }
+
+        let mut saved_pages = HashMap::new();
+        Self::index_saved_pages(root, &mut saved_pages)?;
+        if let Some(index) = ArchiveIndex::load(root)? {
+            for (url, entry) in index.pages {
+                match entry.file {
+                    Some(file) if is_relative_to_root(&file) => {
+                        saved_pages.insert(url_key(&url), root.join(file));
+                    }
+                    Some(file) => log::warn!(
+                        "Ignoring archived page {} outside of {}",
+                        file.display(),
+                        root.display()
+                    ),
+                    None => {}
+                }
+            }
+        }
+        log::info!(
+            "Found {} indexed saved pages in {}",
+            saved_pages.len(),
+            root.display()
+        );
+
+        Ok(LocalPageStore {
+            root: root.to_path_buf(),
+            saved_pages,
+        })
+    }
+
+    fn find_page(&self, url: &str) -> Result<PathBuf, String> {
+        let layout_path = self.root.join(relative_path_for_url(url));
+        if layout_path.is_file() {
+            return Ok(layout_path);
+        }
+
+        self.saved_pages.get(&url_key(url)).cloned().ok_or_else(|| {
+            format!(
+                "No saved page for url {}. Expected it at {}",
+                url,
+                layout_path.display()
+            )
+        })
+    }
+
+    fn index_saved_pages(
+        dir: &Path,
+        saved_pages: &mut HashMap<String, PathBuf>,
+    ) -> Result<(), String> {
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to list directory {}. Error: {}", dir.display(), e))?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                Self::index_saved_pages(&path, saved_pages)?;
+                continue;
+            }
+
+            let is_html = path
+                .extension()
+                .map(|ext| ext == "html" || ext == "htm")
+                .unwrap_or(false);
+            if !is_html {
+                continue;
+            }
+
+            if let Some(url) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|html| canonical_url(&html))
+            {
+                saved_pages.entry(url_key(&url)).or_insert(path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Path, relative to the pages directory, where the page for `url` is stored.
+///
+/// `https://filmow.com/usuario/joe/ja-vi/?pagina=2` is stored at `usuario/joe/ja-vi/pagina-2.html`
+/// and every other page at `<url path>/index.html`. `.` and `..` segments are dropped, so the path
+/// never leads outside the pages directory.
+pub fn relative_path_for_url(url: &str) -> PathBuf {
+    let without_scheme = url.split("://").nth(1).unwrap_or(url);
+    let path_and_query = without_scheme
+        .find('/')
+        .map(|i| &without_scheme[i..])
+        .unwrap_or("/");
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path_and_query, None),
+    };
+
+    let page = query.and_then(|query| {
+        query
+            .split('&')
+            .find_map(|param| param.strip_prefix("pagina="))
+            .filter(|page| *page != "1" && page.chars().all(|c| c.is_ascii_digit()))
+    });
+
+    let mut relative_path: PathBuf = path
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+        .collect();
+    match page {
+        Some(page) => relative_path.push(format!("pagina-{}.html", page)),
+        None => relative_path.push("index.html"),
+    }
+    relative_path
+}
+
+/// Whether `path` stays inside the directory it's relative to.
+fn is_relative_to_root(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Url the page declares as its own through `<link rel="canonical">` or `og:url`.
+fn canonical_url(html: &str) -> Option<String> {
+    let document = Document::from(html);
+    document
+        .find(Name("link").and(Attr("rel", "canonical")))
+        .filter_map(|n| n.attr("href"))
+        .chain(
+            document
+                .find(Name("meta").and(Attr("property", "og:url")))
+                .filter_map(|n| n.attr("content")),
+        )
+        .map(|url| url.to_string())
+        .next()
+}
+
+/// Key that matches the same page regardless of scheme, host and trailing slash.
+fn url_key(url: &str) -> String {
+    relative_path_for_url(url).to_string_lossy().to_string()
+}
+
+#[async_trait]
+impl HttpTransport for LocalPageStore {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        let path = match self.find_page(url) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("{}", e);
+                return Ok(HttpResponse::not_found());
+            }
+        };
+
+        fs::read_to_string(&path)
+            .map(|body| HttpResponse::ok(&body))
+            .map_err(|e| format!("Failed to read saved page {}. Error: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_urls_to_layout_paths() {
+        assert_eq!(
+            relative_path_for_url("https://filmow.com/usuario/joe/ja-vi/?pagina=2"),
+            PathBuf::from("usuario/joe/ja-vi/pagina-2.html")
+        );
+        assert_eq!(
+            relative_path_for_url("https://filmow.com/usuario/joe/ja-vi/?pagina=1"),
+            PathBuf::from("usuario/joe/ja-vi/index.html")
+        );
+        assert_eq!(
+            relative_path_for_url("https://filmow.com/duna-t300/"),
+            PathBuf::from("duna-t300/index.html")
+        );
+    }
+
+    #[test]
+    fn keeps_paths_inside_pages_directory() {
+        assert_eq!(
+            relative_path_for_url("https://filmow.com/../../x/./"),
+            PathBuf::from("x/index.html")
+        );
+        assert_eq!(
+            relative_path_for_url("https://filmow.com/lista/?pagina=../../x"),
+            PathBuf::from("lista/index.html")
+        );
+    }
+}
@@ -1 +1,6 @@
+pub mod cassette;
 pub mod filmow_client;
+pub mod filmow_urls;
+pub mod local_pages;
+pub mod page_archive;
+pub mod transport;
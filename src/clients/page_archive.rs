@@ -0,0 +1,214 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::local_pages::relative_path_for_url;
+
+pub const INDEX_FILE_NAME: &str = "index.json";
+/// Pages recorded between saves of the index, so an interrupted run leaves most of its pages
+/// indexed.
+const INDEX_SAVE_INTERVAL: usize = 25;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Path of the saved page relative to the archive root. Absent when the fetch failed.
+    pub file: Option<PathBuf>,
+    /// Seconds since the unix epoch when the page was fetched.
+    pub fetched_at: u64,
+    pub status: u16,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ArchiveIndex {
+    pub pages: BTreeMap<String, ArchiveEntry>,
+}
+
+impl ArchiveIndex {
+    pub fn load(root: &Path) -> Result<Option<Self>, String> {
+        let index_path = root.join(INDEX_FILE_NAME);
+        if !index_path.is_file() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&index_path).map_err(|e| {
+            format!(
+                "Failed to read archive index {}. Error: {}",
+                index_path.display(),
+                e
+            )
+        })?;
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            format!(
+                "Failed to parse archive index {}. Error: {}",
+                index_path.display(),
+                e
+            )
+        })
+    }
+}
+
+/// Raw copy of every page fetched from Filmow, stored with the layout `LocalPageStore` reads.
+#[derive(Debug)]
+pub struct PageArchive {
+    root: PathBuf,
+    index: Mutex<ArchiveIndex>,
+    /// Pages recorded since the index was last saved.
+    unsaved: AtomicUsize,
+}
+
+impl PageArchive {
+    /// Opens the archive at `root`, keeping the entries of any previous archive stored there.
+    pub fn open(root: &Path) -> Result<Self, String> {
+        fs::create_dir_all(root).map_err(|e| {
+            format!(
+                "Failed to create archive directory {}. Error: {}",
+                root.display(),
+                e
+            )
+        })?;
+
+        Ok(PageArchive {
+            root: root.to_path_buf(),
+            index: Mutex::new(ArchiveIndex::load(root)?.unwrap_or_default()),
+            unsaved: AtomicUsize::new(0),
+        })
+    }
+
+    /// Records the outcome of fetching `url`, saving `body` when the fetch succeeded.
+    pub fn record(&self, url: &str, status: u16, body: Option<&str>) -> Result<(), String> {
+        let file = match body {
+            Some(body) => Some(self.save_page(url, body)?),
+            None => None,
+        };
+
+        let entry = ArchiveEntry {
+            file,
+            fetched_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            status,
+        };
+        self.index
+            .lock()
+            .expect("Archive index lock was poisoned")
+            .pages
+            .insert(url.to_string(), entry);
+        if self.unsaved.fetch_add(1, Ordering::Relaxed) + 1 >= INDEX_SAVE_INTERVAL {
+            self.save_index()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the index next to the pages, replacing the previous one only once it's complete.
+    pub fn save_index(&self) -> Result<PathBuf, String> {
+        let index_path = self.root.join(INDEX_FILE_NAME);
+        let partial_path = index_path.with_extension("json.partial");
+        let index = self.index.lock().expect("Archive index lock was poisoned");
+        self.unsaved.store(0, Ordering::Relaxed);
+        let content = serde_json::to_string_pretty(&*index)
+            .map_err(|e| format!("Failed to serialize archive index. Error: {}", e))?;
+
+        fs::write(&partial_path, content)
+            .and_then(|()| fs::rename(&partial_path, &index_path))
+            .map_err(|e| {
+                format!(
+                    "Failed to write archive index {}. Error: {}",
+                    index_path.display(),
+                    e
+                )
+            })?;
+
+        Ok(index_path)
+    }
+
+    fn save_page(&self, url: &str, body: &str) -> Result<PathBuf, String> {
+        let relative_path = relative_path_for_url(url);
+        let path = self.root.join(&relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                format!(
+                    "Failed to create archive directory {}. Error: {}",
+                    parent.display(),
+                    e
+                )
+            })?;
+        }
+
+        fs::write(&path, body)
+            .map_err(|e| format!("Failed to archive page {}. Error: {}", path.display(), e))?;
+
+        Ok(relative_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_pages_inside_archive_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("archive");
+        let archive = PageArchive::open(&root).unwrap();
+
+        archive
+            .record(
+                "https://filmow.com/../../escaped/",
+                200,
+                Some("<html></html>"),
+            )
+            .unwrap();
+
+        assert!(root.join("escaped/index.html").is_file());
+        assert!(!dir.path().join("escaped").exists());
+    }
+
+    #[test]
+    fn saves_index_while_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = PageArchive::open(dir.path()).unwrap();
+
+        for film in 0..INDEX_SAVE_INTERVAL {
+            let url = format!("https://filmow.com/filme-t{}/", film);
+            archive.record(&url, 200, Some("<html></html>")).unwrap();
+        }
+        drop(archive);
+
+        let index = ArchiveIndex::load(dir.path()).unwrap().unwrap();
+        assert_eq!(index.pages.len(), INDEX_SAVE_INTERVAL);
+    }
+
+    #[test]
+    fn saves_index_while_recording_into_an_existing_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = PageArchive::open(dir.path()).unwrap();
+        archive
+            .record("https://filmow.com/filme-t0/", 200, Some("<html></html>"))
+            .unwrap();
+        archive.save_index().unwrap();
+        drop(archive);
+
+        // Recording a page again doesn't grow the index, but still counts towards the next save.
+        let archive = PageArchive::open(dir.path()).unwrap();
+        for _ in 0..INDEX_SAVE_INTERVAL {
+            archive
+                .record("https://filmow.com/filme-t0/", 404, None)
+                .unwrap();
+        }
+        drop(archive);
+
+        let index = ArchiveIndex::load(dir.path()).unwrap().unwrap();
+        assert_eq!(index.pages["https://filmow.com/filme-t0/"].status, 404);
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use async_trait::async_trait;
+use reqwest::Client;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn ok(body: &str) -> Self {
+        HttpResponse {
+            status: 200,
+            body: body.to_string(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        HttpResponse {
+            status: 404,
+            body: String::new(),
+        }
+    }
+}
+
+/// How the GET requests of a run to Filmow are performed.
+///
+/// Errors are reserved for requests that got no response at all. Implementations are expected
+/// to return error statuses such as 404 or 429 as responses, leaving retries to the client.
+/// Implement it with the `async_trait` crate to route requests through proxies, caches or
+/// recorders.
+#[async_trait]
+pub trait HttpTransport: Send + Sync + Debug {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String>;
+}
+
+/// Default transport, accessing the network through reqwest.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_client(client: Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        let resp = self.client.get(url).send().await.map_err(|e| {
+            format!(
+                "Failed to get HTML for url: {}. Received error: {:?}",
+                url, e
+            )
+        })?;
+
+        let status = resp.status().as_u16();
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| format!("Failed to get text form url {}. Error was {}", url, e))?;
+
+        Ok(HttpResponse { status, body })
+    }
+}
+
+/// In-memory transport answering from a fixed set of pages, and 404 for any other url.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureTransport {
+    responses: HashMap<String, HttpResponse>,
+}
+
+impl FixtureTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_page(self, url: &str, body: &str) -> Self {
+        self.with_response(url, HttpResponse::ok(body))
+    }
+
+    pub fn with_response(mut self, url: &str, response: HttpResponse) -> Self {
+        self.responses.insert(url.to_string(), response);
+        self
+    }
+}
+
+#[async_trait]
+impl HttpTransport for FixtureTransport {
+    async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+        Ok(self
+            .responses
+            .get(url)
+            .cloned()
+            .unwrap_or_else(HttpResponse::not_found))
+    }
+}
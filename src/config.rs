@@ -0,0 +1,80 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    clients::{filmow_urls::DEFAULT_BASE_URL, transport::HttpTransport},
+    events::ExportObserver,
+    model::collection::Collection,
+    persisters::{exporter::Exporter, letterboxd_csv_exporter::ChunkLimits},
+    transformers::{deduplicator::OverlapPolicy, rating_transformer::RatingOptions},
+};
+
+/// Options controlling what a run exports and where the generated files end up.
+///
+/// Start from `RunConfig::default()`, or use `ExportBuilder`, and change the fields that differ.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RunConfig {
+    /// Collections fetched from the profile.
+    pub collections: Vec<Collection>,
+    /// Names of the exporters every collection is written with: the built-in `letterboxd-csv`
+    /// and `json`, or the name of one of `custom_exporters`.
+    pub exporters: Vec<String>,
+    /// Exporters available next to the built-in ones, selected by name in `exporters`.
+    pub custom_exporters: Vec<Arc<dyn Exporter>>,
+    pub output_dir: PathBuf,
+    pub rating: RatingOptions,
+    /// Directory of saved Filmow pages used instead of the network when set.
+    pub offline_dir: Option<PathBuf>,
+    /// Directory where a raw copy of every fetched page is kept when set.
+    pub archive_dir: Option<PathBuf>,
+    /// Root every Filmow url is built from. Defaults to https://filmow.com.
+    pub base_url: String,
+    /// Transport used for every request instead of the default reqwest one when set.
+    pub transport: Option<Arc<dyn HttpTransport>>,
+    /// Cassette file where every request and response of the run is recorded when set.
+    pub record_cassette: Option<PathBuf>,
+    /// Cassette file whose recorded responses are served instead of accessing Filmow when set.
+    pub replay_cassette: Option<PathBuf>,
+    /// JSON file overriding the selectors used to read Filmow pages, in the format of
+    /// `default_selectors_json`.
+    pub selectors_file: Option<PathBuf>,
+    /// Prints the number of pages and films fetched so far, with an ETA, to stderr.
+    pub show_progress: bool,
+    /// Sorts every exported file by title once the run is done. Otherwise movies are left in
+    /// the order they were fetched in.
+    pub sort_output: bool,
+    /// What to do with films that are both watched and on the watchlist.
+    pub overlap_policy: OverlapPolicy,
+    /// Limits the CSV files of the `letterboxd-csv` exporter are split at.
+    pub csv_chunks: ChunkLimits,
+    /// Letterboxd data export whose films are left out of the generated files when set: the
+    /// ZIP downloaded from Letterboxd, the folder it was extracted to or one of its CSV files.
+    pub letterboxd_export: Option<PathBuf>,
+    /// Receive every page fetched, film read and file written as the run goes.
+    pub observers: Vec<Arc<dyn ExportObserver>>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            collections: Collection::all(),
+            exporters: vec!["letterboxd-csv".to_string()],
+            custom_exporters: vec![],
+            output_dir: PathBuf::from("."),
+            rating: RatingOptions::default(),
+            offline_dir: None,
+            archive_dir: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            transport: None,
+            record_cassette: None,
+            replay_cassette: None,
+            selectors_file: None,
+            show_progress: false,
+            sort_output: true,
+            overlap_policy: OverlapPolicy::default(),
+            csv_chunks: ChunkLimits::default(),
+            letterboxd_export: None,
+            observers: vec![],
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    exported_files::{read_export, ExportedFilm},
+    letterboxd_export::normalize_title,
+    model::collection::Collection,
+};
+
+/// How a film changed between two exports of the same profile.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum FilmChange {
+    Added {
+        collection: Collection,
+        film: ExportedFilm,
+    },
+    Removed {
+        collection: Collection,
+        film: ExportedFilm,
+    },
+    /// Rated differently, or rated or unrated since. `film` holds the new rating.
+    Rerated {
+        collection: Collection,
+        film: ExportedFilm,
+        previous_rating: Option<f32>,
+    },
+    /// Left `from` and added to `to`, such as a watchlist film since watched.
+    Moved {
+        from: Collection,
+        to: Collection,
+        film: ExportedFilm,
+    },
+}
+
+/// Changes between an older and a newer export of the same profile.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct ExportDiff {
+    pub changes: Vec<FilmChange>,
+}
+
+impl ExportDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for ExportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mut added, mut removed, mut rerated, mut moved) = (0, 0, 0, 0);
+        for change in &self.changes {
+            match change {
+                FilmChange::Added { collection, film } => {
+                    added += 1;
+                    writeln!(f, "Added to {}: {}", collection, film)?
+                }
+                FilmChange::Removed { collection, film } => {
+                    removed += 1;
+                    writeln!(f, "Removed from {}: {}", collection, film)?
+                }
+                FilmChange::Rerated {
+                    collection,
+                    film,
+                    previous_rating,
+                } => {
+                    rerated += 1;
+                    writeln!(
+                        f,
+                        "Re-rated in {}: {} from {} to {}",
+                        collection,
+                        film,
+                        rating_text(*previous_rating),
+                        rating_text(film.rating)
+                    )?
+                }
+                FilmChange::Moved { from, to, film } => {
+                    moved += 1;
+                    writeln!(f, "Moved from {} to {}: {}", from, to, film)?
+                }
+            }
+        }
+
+        if self.is_empty() {
+            write!(f, "No changes")
+        } else {
+            write!(
+                f,
+                "{} added, {} removed, {} re-rated and {} moved",
+                added, removed, rerated, moved
+            )
+        }
+    }
+}
+
+/// Compares the export at `old` with the later one at `new`, each a directory an export was
+/// written to or a single CSV or JSON file. Only collections found in both are compared.
+pub fn diff_exports(old: &Path, new: &Path) -> Result<ExportDiff, String> {
+    let old_films = read_export(old)?;
+    let new_films = read_export(new)?;
+    let collections: Vec<_> = Collection::all()
+        .into_iter()
+        .filter(|collection| {
+            old_films.contains_key(collection) && new_films.contains_key(collection)
+        })
+        .collect();
+    if collections.is_empty() {
+        return Err(format!(
+            "{} and {} have no collection in common",
+            old.display(),
+            new.display()
+        ));
+    }
+
+    let mut added = BTreeMap::new();
+    let mut removed = BTreeMap::new();
+    let mut rerated = vec![];
+    for collection in &collections {
+        let old_by_key = by_key(&old_films[collection]);
+        let new_by_key = by_key(&new_films[collection]);
+
+        for (key, film) in &new_by_key {
+            match old_by_key.get(key) {
+                None => added
+                    .entry(key.clone())
+                    .or_insert_with(Vec::new)
+                    .push((*collection, (*film).clone())),
+                Some(old_film) if ratings_differ(old_film.rating, film.rating) => {
+                    rerated.push(FilmChange::Rerated {
+                        collection: *collection,
+                        film: (*film).clone(),
+                        previous_rating: old_film.rating,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, film) in old_by_key {
+            if !new_by_key.contains_key(&key) {
+                removed
+                    .entry(key)
+                    .or_insert_with(Vec::new)
+                    .push((*collection, film.clone()));
+            }
+        }
+    }
+
+    let mut moved = vec![];
+    for (key, from) in removed.iter_mut() {
+        let Some(to) = added.get_mut(key) else {
+            continue;
+        };
+        if let (Some(from), Some(to)) = (from.pop(), to.pop()) {
+            moved.push(FilmChange::Moved {
+                from: from.0,
+                to: to.0,
+                film: to.1,
+            });
+        }
+    }
+
+    let mut changes: Vec<_> = added
+        .into_values()
+        .flatten()
+        .map(|(collection, film)| FilmChange::Added { collection, film })
+        .collect();
+    changes.extend(
+        removed
+            .into_values()
+            .flatten()
+            .map(|(collection, film)| FilmChange::Removed { collection, film }),
+    );
+    changes.extend(rerated);
+    changes.extend(moved);
+    Ok(ExportDiff { changes })
+}
+
+/// Films of a collection keyed by normalized title and year, in title order.
+fn by_key(films: &[ExportedFilm]) -> BTreeMap<(String, u32), &ExportedFilm> {
+    films
+        .iter()
+        .map(|film| ((normalize_title(&film.title), film.year), film))
+        .collect()
+}
+
+fn ratings_differ(old: Option<f32>, new: Option<f32>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => (old - new).abs() > f32::EPSILON,
+        (old, new) => old.is_some() != new.is_some(),
+    }
+}
+
+fn rating_text(rating: Option<f32>) -> String {
+    match rating {
+        Some(rating) => rating.to_string(),
+        None => "unrated".to_string(),
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt;
+
+use select::document::Document;
+use serde::Serialize;
+
+use crate::{
+    clients::filmow_client::FilmowClient,
+    extractors::{selector::SelectorChain, structured_data::FieldSource},
+    model::collection::Collection,
+};
+
+/// Length of the html excerpts attached to failed checks.
+const SNIPPET_LENGTH: usize = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum CheckStatus {
+    Ok,
+    /// The value was only found through a fallback, so the primary selectors stopped working.
+    Degraded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct CheckResult {
+    pub page_url: String,
+    pub check: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// Excerpt of the page html around where the selectors stopped matching, for checks that
+    /// did not pass.
+    pub snippet: Option<String>,
+}
+
+/// Outcome of running every extractor against a known profile and some of its film pages.
+#[derive(Debug, Clone, Default, Serialize)]
+#[non_exhaustive]
+pub struct DoctorReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    pub fn is_healthy(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status == CheckStatus::Ok)
+    }
+
+    fn push(
+        &mut self,
+        page_url: &str,
+        check: &str,
+        status: CheckStatus,
+        detail: String,
+        snippet: Option<String>,
+    ) {
+        self.checks.push(CheckResult {
+            page_url: page_url.to_string(),
+            check: check.to_string(),
+            status,
+            detail,
+            snippet,
+        });
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                CheckStatus::Ok => "OK",
+                CheckStatus::Degraded => "DEGRADED",
+                CheckStatus::Failed => "FAILED",
+            };
+            writeln!(
+                f,
+                "[{:>8}] {} ({}): {}",
+                status, check.check, check.page_url, check.detail
+            )?;
+            if let Some(snippet) = &check.snippet {
+                writeln!(f, "           html: {}", snippet)?;
+            }
+        }
+
+        let problems = self
+            .checks
+            .iter()
+            .filter(|check| check.status != CheckStatus::Ok)
+            .count();
+        if problems == 0 {
+            write!(f, "All {} checks passed", self.checks.len())
+        } else {
+            write!(
+                f,
+                "{} of {} checks did not pass. Filmow may have changed its layout",
+                problems,
+                self.checks.len()
+            )
+        }
+    }
+}
+
+/// Fetches the listing pages of `profile` and up to `max_films` of their film pages, reporting
+/// which extractors still understand them.
+pub async fn diagnose(client: &FilmowClient, profile: &str, max_films: usize) -> DoctorReport {
+    let mut report = DoctorReport::default();
+    let mut film_urls = vec![];
+
+    for collection in Collection::all() {
+        let url = client.urls().collection_page(collection, profile, 1);
+        let html = match client.get_html_from_url(&url).await {
+            Ok(html) => html,
+            Err(e) => {
+                report.push(
+                    &url,
+                    &format!("{} page", collection),
+                    CheckStatus::Failed,
+                    format!("Could not fetch page: {}", e),
+                    None,
+                );
+                continue;
+            }
+        };
+
+        let extractor = client.extractor();
+        let (movies, entry_selectors) = match collection {
+            Collection::Watched => (
+                extractor.get_preliminary_info_for_watched_movies(&html, client.urls()),
+                &extractor.selectors().watched_movie_item,
+            ),
+            Collection::Watchlist => (
+                extractor.get_preliminary_info_for_watchlist(&html, client.urls()),
+                &extractor.selectors().watchlist_movie_link,
+            ),
+        };
+
+        if movies.is_empty() {
+            report.push(
+                &url,
+                &format!("{} entries", collection),
+                CheckStatus::Failed,
+                "No films found in the listing".to_string(),
+                Some(snippet_near(&html, entry_selectors)),
+            );
+        } else {
+            report.push(
+                &url,
+                &format!("{} entries", collection),
+                CheckStatus::Ok,
+                format!("Found {} films", movies.len()),
+                None,
+            );
+        }
+
+        let rating_errors: Vec<&String> = movies
+            .iter()
+            .filter_map(|movie| movie.rating.as_ref().err())
+            .collect();
+        if collection == Collection::Watched && !movies.is_empty() {
+            match rating_errors.first() {
+                Some(error) => report.push(
+                    &url,
+                    "watched ratings",
+                    CheckStatus::Failed,
+                    format!(
+                        "{} of {} ratings could not be parsed",
+                        rating_errors.len(),
+                        movies.len()
+                    ),
+                    Some(excerpt(error)),
+                ),
+                None => report.push(
+                    &url,
+                    "watched ratings",
+                    CheckStatus::Ok,
+                    format!(
+                        "{} rated films",
+                        movies
+                            .iter()
+                            .filter(|movie| matches!(movie.rating, Ok(Some(_))))
+                            .count()
+                    ),
+                    None,
+                ),
+            }
+        }
+
+        let pagination = extractor.get_pagination(&html);
+        report.push(
+            &url,
+            &format!("{} pagination", collection),
+            CheckStatus::Ok,
+            match (pagination.next_page, pagination.highest_page) {
+                (Some(next_page), _) => format!("Next page link found: {}", next_page),
+                (None, Some(highest_page)) => format!("Highest page found: {}", highest_page),
+                (None, None) => "No later pages found".to_string(),
+            },
+            None,
+        );
+
+        film_urls.extend(movies.into_iter().map(|movie| movie.movie_url));
+    }
+
+    film_urls.sort();
+    film_urls.dedup();
+    for url in film_urls.into_iter().take(max_films) {
+        diagnose_film_page(client, &url, &mut report).await;
+    }
+
+    report
+}
+
+async fn diagnose_film_page(client: &FilmowClient, url: &str, report: &mut DoctorReport) {
+    let html = match client.get_html_from_url(url).await {
+        Ok(html) => html,
+        Err(e) => {
+            return report.push(
+                url,
+                "film page",
+                CheckStatus::Failed,
+                format!("Could not fetch page: {}", e),
+                None,
+            )
+        }
+    };
+
+    let extraction = client.extractor().extract_fields(&html);
+    let selectors = client.extractor().selectors();
+    let fields = [
+        (
+            "title",
+            extraction.title.clone(),
+            extraction.sources.title,
+            &selectors.title,
+        ),
+        (
+            "director",
+            extraction.director.clone(),
+            extraction.sources.director,
+            &selectors.director,
+        ),
+        (
+            "year",
+            extraction.year.map(|year| year.to_string()),
+            extraction.sources.year,
+            &selectors.year,
+        ),
+    ];
+
+    for (field, value, source, field_selectors) in fields {
+        let check = format!("film {}", field);
+        match (value, source) {
+            (Some(value), Some(FieldSource::Html)) => {
+                report.push(url, &check, CheckStatus::Ok, value, None)
+            }
+            (Some(value), Some(source)) => report.push(
+                url,
+                &check,
+                CheckStatus::Degraded,
+                format!("'{}' only found in {}", value, source),
+                Some(snippet_near(&html, field_selectors)),
+            ),
+            _ => report.push(
+                url,
+                &check,
+                CheckStatus::Failed,
+                "Not found".to_string(),
+                Some(snippet_near(&html, field_selectors)),
+            ),
+        }
+    }
+}
+
+/// Excerpt of the html around the node closest to what `selectors` look for, or of the body of
+/// the page when nothing in it resembles their target.
+fn snippet_near(html: &str, selectors: &SelectorChain) -> String {
+    let document = Document::from(html);
+    match selectors.closest(&document) {
+        Some(node) => excerpt(&node.parent().unwrap_or(node).html()),
+        None => excerpt(
+            html.find("<body")
+                .map(|start| &html[start..])
+                .unwrap_or(html),
+        ),
+    }
+}
+
+/// `text` with its whitespace collapsed, cut after `SNIPPET_LENGTH` characters.
+fn excerpt(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match collapsed.char_indices().nth(SNIPPET_LENGTH) {
+        Some((end, _)) => format!("{}...", &collapsed[..end]),
+        None => collapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILM_PAGE: &str = r#"<html><head><title>Central do Brasil - Filmow</title></head>
+        <body>
+            <div class="movie-title">
+                <h1 itemprop="name">Central do Brasil</h1>
+                <small class="release">1998</small>
+            </div>
+            <div class="directors">Direção: </div>
+        </body></html>"#;
+
+    fn chain(selectors: &[&str]) -> SelectorChain {
+        SelectorChain::parse(selectors).unwrap()
+    }
+
+    #[test]
+    fn shows_container_of_partial_matches() {
+        let snippet = snippet_near(FILM_PAGE, &chain(&["h1.movie-original-title"]));
+        assert_eq!(
+            snippet,
+            r#"<div class="movie-title"> <h1 itemprop="name">Central do Brasil</h1> <small class="release">1998</small> </div>"#
+        );
+        assert!(
+            snippet_near(FILM_PAGE, &chain(&["span[itemprop=director]"]))
+                .contains(r#"itemprop="name""#)
+        );
+    }
+
+    #[test]
+    fn shows_body_when_nothing_resembles_the_selector() {
+        let snippet = snippet_near(FILM_PAGE, &chain(&["table.cast"]));
+        assert!(snippet.starts_with("<body>"));
+        assert!(!snippet.contains("<title>"));
+    }
+
+    #[test]
+    fn cuts_long_excerpts() {
+        let snippet = excerpt(&"a ".repeat(SNIPPET_LENGTH));
+        assert_eq!(snippet.len(), SNIPPET_LENGTH + 3);
+        assert!(snippet.ends_with("..."));
+    }
+}
@@ -0,0 +1,66 @@
+use std::{fmt::Debug, path::PathBuf};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::model::{collection::Collection, movie::Movie};
+
+/// Something that happened while a collection was fetched or exported.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ExportEvent {
+    /// A listing page became known to exist, such as from the paginator of a fetched page. Sent
+    /// before the page is fetched.
+    PageDiscovered {
+        collection: Collection,
+        page: usize,
+    },
+    PageFetched {
+        collection: Collection,
+        page: usize,
+    },
+    /// A listing page couldn't be fetched, so its films are missing from the export.
+    PageFailed {
+        collection: Collection,
+        url: String,
+        reason: String,
+    },
+    /// The page of a film was read, before its rating is transformed.
+    FilmResolved {
+        collection: Collection,
+        url: String,
+        movie: Movie,
+    },
+    FilmFailed {
+        collection: Collection,
+        url: String,
+        reason: String,
+    },
+    /// The rating of a film couldn't be understood, so the film is exported unrated.
+    RatingFailed {
+        collection: Collection,
+        url: String,
+        reason: String,
+    },
+    /// An exporter finished writing a file.
+    ExportWritten {
+        collection: Collection,
+        exporter: String,
+        file: PathBuf,
+    },
+}
+
+/// Receives the events of a run as they happen, such as to show live progress.
+///
+/// Events are delivered from the tasks fetching the collections, so implementations should
+/// return quickly. Add them to `RunConfig::observers`.
+pub trait ExportObserver: Send + Sync + Debug {
+    fn on_event(&self, event: &ExportEvent);
+}
+
+/// Forwards every event to a channel, to be consumed as a stream.
+impl ExportObserver for UnboundedSender<ExportEvent> {
+    fn on_event(&self, event: &ExportEvent) {
+        // Nobody listening anymore isn't a reason to stop the export.
+        let _ = self.send(event.clone());
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, StringRecord};
+use serde::Serialize;
+
+use crate::{
+    model::{collection::Collection, movie::Movie},
+    persisters::letterboxd_csv_exporter::LetterboxdColumn,
+};
+
+/// A film read back from the files generated by an export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ExportedFilm {
+    pub title: String,
+    pub year: u32,
+    /// Rating from 0.5 to 5 stars, converted from `Rating10` when exported in that scale. Ratings
+    /// of JSON files are read as written.
+    pub rating: Option<f32>,
+}
+
+impl fmt::Display for ExportedFilm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.title, self.year)
+    }
+}
+
+/// Reads the films of every collection exported to `path`: a directory an export was written
+/// to, or a single CSV or JSON file named after its collection.
+pub fn read_export(path: &Path) -> Result<HashMap<Collection, Vec<ExportedFilm>>, String> {
+    let mut files = HashMap::new();
+    if path.is_dir() {
+        for collection in Collection::all() {
+            let mut parts = collection_files(path, collection, "csv")?;
+            if parts.is_empty() {
+                parts = collection_files(path, collection, "json")?;
+            }
+            if !parts.is_empty() {
+                files.insert(collection, parts);
+            }
+        }
+    } else {
+        let collection = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| {
+                Collection::all().into_iter().find(|collection| {
+                    ["csv", "json"]
+                        .iter()
+                        .any(|extension| part_number(name, *collection, extension).is_some())
+                })
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Can't tell the collection of {}. Expected a file named like watched.csv \
+                     or watchlist.json",
+                    path.display()
+                )
+            })?;
+        files.insert(collection, vec![path.to_path_buf()]);
+    }
+
+    if files.is_empty() {
+        return Err(format!("No exported files found in {}", path.display()));
+    }
+
+    let mut films = HashMap::new();
+    for (collection, parts) in files {
+        let mut collection_films = vec![];
+        for part in parts {
+            collection_films.extend(read_films(&part)?);
+        }
+        films.insert(collection, collection_films);
+    }
+    Ok(films)
+}
+
+/// Files generated for `collection` in `dir` with `extension`, either a single `<stem>.<ext>`
+/// or its numbered parts in order.
+pub fn collection_files(
+    dir: &Path,
+    collection: Collection,
+    extension: &str,
+) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}. Error: {}", dir.display(), e))?;
+
+    let mut parts = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read directory {}. Error: {}", dir.display(), e))?
+            .path();
+        let part = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| part_number(name, collection, extension));
+        if let Some(part) = part {
+            parts.push((part, path));
+        }
+    }
+
+    parts.sort();
+    Ok(parts.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Film of a CSV row with the given header columns, unless its title or year is missing.
+pub fn film_from_record(
+    record: &StringRecord,
+    columns: &[Option<LetterboxdColumn>],
+) -> Option<ExportedFilm> {
+    let field = |column: LetterboxdColumn| {
+        columns
+            .iter()
+            .position(|c| *c == Some(column))
+            .and_then(|index| record.get(index))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let rating = match field(LetterboxdColumn::Rating) {
+        Some(rating) => rating.parse::<f32>().ok(),
+        None => field(LetterboxdColumn::Rating10)
+            .and_then(|rating| rating.parse::<f32>().ok())
+            .map(|rating| rating / 2.0),
+    };
+
+    Some(ExportedFilm {
+        title: field(LetterboxdColumn::Title)?.to_string(),
+        year: field(LetterboxdColumn::Year)?.parse().ok()?,
+        rating,
+    })
+}
+
+/// Part number of `file_name` when it was generated for `collection`, 0 when it wasn't split.
+fn part_number(file_name: &str, collection: Collection, extension: &str) -> Option<usize> {
+    let rest = file_name
+        .strip_prefix(collection.file_stem())?
+        .strip_suffix(extension)?
+        .strip_suffix('.')?;
+    match rest.strip_prefix('-') {
+        _ if rest.is_empty() => Some(0),
+        Some(part) => part.parse().ok(),
+        None => None,
+    }
+}
+
+fn read_films(path: &Path) -> Result<Vec<ExportedFilm>, String> {
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if is_json {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}. Error: {}", path.display(), e))?;
+        let movies: Vec<Movie> = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid JSON file {}. Error: {}", path.display(), e))?;
+        return Ok(movies
+            .into_iter()
+            .map(|movie| ExportedFilm {
+                title: movie.title,
+                year: movie.year,
+                rating: movie.rating,
+            })
+            .collect());
+    }
+
+    let mut rdr = ReaderBuilder::new()
+        .from_path(path)
+        .map_err(|e| format!("Failed to read {}. Error: {}", path.display(), e))?;
+    let columns: Vec<_> = rdr
+        .headers()
+        .map_err(|e| format!("Invalid CSV file {}. Error: {}", path.display(), e))?
+        .iter()
+        .map(LetterboxdColumn::from_name)
+        .collect();
+
+    let mut films = vec![];
+    for record in rdr.records() {
+        let record =
+            record.map_err(|e| format!("Invalid CSV file {}. Error: {}", path.display(), e))?;
+        match film_from_record(&record, &columns) {
+            Some(film) => films.push(film),
+            None => log::warn!(
+                "Ignoring row of {} without a title and year: {:?}",
+                path.display(),
+                record
+            ),
+        }
+    }
+    Ok(films)
+}
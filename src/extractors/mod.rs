@@ -1 +1,5 @@
 pub mod movie_extractor;
+pub mod rating_extractor;
+pub mod selector;
+pub mod selector_config;
+pub mod structured_data;
@@ -1,153 +1,260 @@
-use select::{
-    document::Document,
-    predicate::{And, Class, Name},
-};
+use select::document::Document;
+use serde::Serialize;
 
-use std::collections::HashSet;
+use std::{collections::HashSet, sync::Arc};
 
 use crate::{
-    clients::filmow_client::{FilmowClient, PreliminaryMovieInformation},
+    clients::{filmow_client::PreliminaryMovieInformation, filmow_urls::FilmowUrls},
+    extractors::{
+        rating_extractor::RatingExtractor,
+        selector_config::SelectorConfig,
+        structured_data::{FieldSource, StructuredData},
+    },
     model::movie::Movie,
 };
 
-#[derive(Debug)]
-pub struct MovieExtractor {}
+/// Which source supplied each field of a film page, `None` when no source had it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FieldSources {
+    pub title: Option<FieldSource>,
+    pub director: Option<FieldSource>,
+    pub year: Option<FieldSource>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MovieExtraction {
+    pub title: Option<String>,
+    pub director: Option<String>,
+    pub year: Option<u32>,
+    pub sources: FieldSources,
+}
+
+/// Later pages a listing page links to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pagination {
+    /// Page its next link points to.
+    pub next_page: Option<i32>,
+    /// Highest page in its paginator, which may only show the pages around the current one.
+    pub highest_page: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MovieExtractor {
+    selectors: Arc<SelectorConfig>,
+}
 
 impl MovieExtractor {
-    pub fn extract_movie_from_html(html_body: &str, url: &str) -> Result<Movie, String> {
-        let title = MovieExtractor::extract_title(html_body);
-        let director = MovieExtractor::extract_director(html_body);
-        let year = MovieExtractor::extract_year(html_body);
+    pub fn new(selectors: SelectorConfig) -> Self {
+        MovieExtractor {
+            selectors: Arc::new(selectors),
+        }
+    }
+
+    pub fn selectors(&self) -> &SelectorConfig {
+        &self.selectors
+    }
+
+    pub fn extract_movie_from_html(&self, html_body: &str, url: &str) -> Result<Movie, String> {
+        let extraction = self.extract_fields(html_body);
+
+        for (field, source) in [
+            ("title", extraction.sources.title),
+            ("director", extraction.sources.director),
+            ("year", extraction.sources.year),
+        ] {
+            if let Some(source) = source.filter(|s| *s != FieldSource::Html) {
+                log::warn!(
+                    "Extracted {} of page {} from {}, the page markup may have changed",
+                    field,
+                    url,
+                    source
+                );
+            }
+        }
 
-        if title.is_none() {
+        if extraction.title.is_none() {
             return Err(format!("Could not extract title from page: {}", url));
         }
 
-        if director.is_none() {
+        if extraction.director.is_none() {
             log::error!(
                 "Could not extract director from page: {}, but continuing",
                 url
             );
         }
 
-        if year.is_none() {
+        if extraction.year.is_none() {
             return Err(format!("Could not extract year from page: {}", url));
         }
 
         Ok(Movie {
-            title: title.unwrap(),
-            director: director,
-            year: year.unwrap(),
+            title: extraction.title.unwrap(),
+            director: extraction.director,
+            year: extraction.year.unwrap(),
             rating: None,
         })
     }
 
-    fn extract_title(resp: &str) -> Option<String> {
-        Document::from(resp)
-            .find(Name("h2"))
-            .filter(|n| {
-                n.attr("class").is_some() && n.attr("class").unwrap() == "movie-original-title"
-            })
-            .map(|n| n.text())
-            .next()
+    /// Reads every field of a film page, falling back from the visible markup to JSON-LD and
+    /// then to meta tags, and recording which of them supplied each field.
+    pub fn extract_fields(&self, html_body: &str) -> MovieExtraction {
+        let document = Document::from(html_body);
+        let json_ld = StructuredData::from_json_ld(&document);
+        let meta_tags = StructuredData::from_meta_tags(&document);
+
+        let (title, title_source) = first_available([
+            (self.extract_title(&document), FieldSource::Html),
+            (json_ld.title, FieldSource::JsonLd),
+            (meta_tags.title, FieldSource::MetaTags),
+        ]);
+        let (director, director_source) = first_available([
+            (self.extract_director(&document), FieldSource::Html),
+            (json_ld.director, FieldSource::JsonLd),
+            (meta_tags.director, FieldSource::MetaTags),
+        ]);
+        let (year, year_source) = first_available([
+            (self.extract_year(&document), FieldSource::Html),
+            (json_ld.year, FieldSource::JsonLd),
+            (meta_tags.year, FieldSource::MetaTags),
+        ]);
+
+        MovieExtraction {
+            title,
+            director,
+            year,
+            sources: FieldSources {
+                title: title_source,
+                director: director_source,
+                year: year_source,
+            },
+        }
     }
 
-    fn extract_director(resp: &str) -> Option<String> {
-        Document::from(resp)
-            .find(Name("span"))
-            .filter(|n| n.attr("itemprop").is_some() && n.attr("itemprop").unwrap() == "director")
-            .map(|n| n.text().trim().to_string())
-            .next()
+    fn extract_title(&self, document: &Document) -> Option<String> {
+        self.selectors.title.first_value(document)
     }
 
-    fn extract_year(resp: &str) -> Option<u32> {
-        Document::from(resp)
-            .find(Name("small"))
-            .filter(|n| n.attr("class").is_some() && n.attr("class").unwrap() == "release")
-            .map(|n| n.text())
-            .next()
-            .and_then(|s| match s.parse::<u32>() {
-                Ok(num) => Some(num),
-                Err(_e) => None,
-            })
+    fn extract_director(&self, document: &Document) -> Option<String> {
+        self.selectors.director.first_value(document)
+    }
+
+    fn extract_year(&self, document: &Document) -> Option<u32> {
+        self.selectors
+            .year
+            .all_values(document)
+            .iter()
+            .find_map(|s| s.parse::<u32>().ok())
     }
 
     pub fn get_preliminary_info_for_watchlist(
+        &self,
         watchlist_page_html: &str,
+        urls: &FilmowUrls,
     ) -> Vec<PreliminaryMovieInformation> {
         // We convert into a set to remove duplicates, because the page has <a> with hrefs for
         // both the title and the poster of the movie.
-        let movie_urls: HashSet<_> = Document::from(watchlist_page_html)
-            .find(Name("a"))
-            .filter(|n| n.attr("data-movie-pk").is_some())
-            .map(|n| n.attr("href"))
-            .flatten()
-            .map(|x| FilmowClient::get_base_url() + &x.to_string())
+        let movie_urls: HashSet<_> = self
+            .selectors
+            .watchlist_movie_link
+            .all_values(&Document::from(watchlist_page_html))
+            .iter()
+            .map(|href| urls.resolve(href))
             .collect();
 
-        return movie_urls
+        movie_urls
             .into_iter()
             .map(|url| PreliminaryMovieInformation {
                 movie_url: url,
-                rating: None,
+                rating: Ok(None),
             })
-            .collect();
+            .collect()
     }
 
     pub fn get_preliminary_info_for_watched_movies(
+        &self,
         watched_page_html: &str,
+        urls: &FilmowUrls,
     ) -> Vec<PreliminaryMovieInformation> {
-        let html_per_movie = MovieExtractor::break_watched_movies_html_per_movie(watched_page_html);
+        let html_per_movie = self.break_watched_movies_html_per_movie(watched_page_html);
 
         match html_per_movie {
             Ok(html_vec) => html_vec
                 .iter()
-                .map(|movie_html| MovieExtractor::extract_watched_movie_info(movie_html))
-                .flatten()
+                .filter_map(|movie_html| self.extract_watched_movie_info(movie_html, urls))
                 .collect(),
             _ => vec![],
         }
     }
 
-    pub fn get_last_page_from_html(page_html: &str) -> Option<i32> {
+    pub fn get_pagination(&self, page_html: &str) -> Pagination {
         let document = Document::from(page_html);
 
         log::debug!("Retrieved html: {}", page_html);
 
-        document
-            .find(Name("a"))
-            .flat_map(|n| n.attr("href"))
-            .flat_map(|link| {
-                let page_num_str = link.split("pagina=").nth(1);
-                page_num_str.map(|num_str| num_str.parse::<i32>().unwrap())
+        Pagination {
+            next_page: self
+                .selectors
+                .next_page_link
+                .all_values(&document)
+                .iter()
+                .find_map(|link| self.page_number(link)),
+            highest_page: self
+                .selectors
+                .pagination_link
+                .all_values(&document)
+                .iter()
+                .filter_map(|link| self.page_number(link))
+                .max(),
+        }
+    }
+
+    /// Page number of a pagination link such as `?pagina=3&ordem=1`, whatever other query
+    /// parameters it has.
+    fn page_number(&self, link: &str) -> Option<i32> {
+        let page_prefix = format!("{}=", self.selectors.page_parameter);
+        link.split(['?', '&', '#'])
+            .filter_map(|param| param.strip_prefix(page_prefix.as_str()))
+            .find_map(|value| {
+                let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse().ok()
             })
-            .last()
     }
 
-    fn extract_watched_movie_info(watched_movie_html: &str) -> Option<PreliminaryMovieInformation> {
+    fn extract_watched_movie_info(
+        &self,
+        watched_movie_html: &str,
+        urls: &FilmowUrls,
+    ) -> Option<PreliminaryMovieInformation> {
         let document = Document::from(watched_movie_html);
-        let url = document.find(Name("a")).map(|n| n.attr("href")).next();
+        let url = self.selectors.watched_movie_link.first_value(&document);
 
-        let rating: Option<f32> = Document::from(watched_movie_html)
-            .find(And(Name("span"), Class("stars")))
-            .flat_map(|n| n.attr("title"))
-            .flat_map(|s| s.split_ascii_whitespace().nth(1))
-            .flat_map(|s| s.parse::<f32>())
-            .next();
+        let rating = RatingExtractor::extract_rating(&document, &self.selectors.rating_widget);
 
         Some(PreliminaryMovieInformation {
-            movie_url: FilmowClient::get_base_url() + url??,
+            movie_url: urls.resolve(&url?),
             rating,
         })
     }
 
     fn break_watched_movies_html_per_movie(
+        &self,
         full_watched_page_html: &str,
     ) -> Result<Vec<String>, String> {
-        Ok(Document::from(full_watched_page_html)
-            .find(And(Name("li"), Class("movie_list_item")))
-            .filter(|n| n.attr("data-movie-pk").is_some())
+        Ok(self
+            .selectors
+            .watched_movie_item
+            .find(&Document::from(full_watched_page_html))
+            .iter()
             .map(|n| n.html())
             .collect())
     }
 }
+
+fn first_available<T>(
+    candidates: [(Option<T>, FieldSource); 3],
+) -> (Option<T>, Option<FieldSource>) {
+    candidates
+        .into_iter()
+        .find_map(|(value, source)| value.map(|value| (Some(value), Some(source))))
+        .unwrap_or((None, None))
+}
@@ -0,0 +1,196 @@
+use crate::extractors::selector::SelectorChain;
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Attr, Class, Predicate},
+};
+
+const MIN_RATING: f32 = 0.5;
+const MAX_RATING: f32 = 5.0;
+
+/// Data attributes Filmow has used to carry the numeric rating of a `span.stars` widget.
+const RATING_ATTRIBUTES: [&str; 3] = ["data-rating", "data-value", "data-score"];
+
+#[derive(Debug)]
+pub struct RatingExtractor {}
+
+impl RatingExtractor {
+    /// Extracts the five star rating from a single watched movie entry.
+    ///
+    /// Returns `Ok(None)` when the entry has no rating widget, meaning the movie was not rated,
+    /// and an error when a widget is present but its rating can't be understood.
+    pub fn extract_rating(
+        document: &Document,
+        rating_widget: &SelectorChain,
+    ) -> Result<Option<f32>, String> {
+        let stars = match rating_widget.find(document).into_iter().next() {
+            Some(stars) => stars,
+            None => return Ok(None),
+        };
+
+        let rating = Self::rating_from_attributes(&stars)
+            .or_else(|| stars.attr("title").and_then(Self::parse_rating_text))
+            .or_else(|| Self::rating_from_star_count(&stars))
+            .or_else(|| Self::rating_from_width(&stars));
+
+        match rating {
+            Some(rating) => Self::validate(rating).map(Some),
+            None => Err(format!(
+                "Could not understand rating widget: {}",
+                stars.html().trim()
+            )),
+        }
+    }
+
+    /// Finds the first number of a text such as "Nota 4.5", "Nota: 4,5" or "4,5 estrelas".
+    pub fn parse_rating_text(text: &str) -> Option<f32> {
+        let start = text.find(|c: char| c.is_ascii_digit())?;
+        let number: String = text[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+            .map(|c| if c == ',' { '.' } else { c })
+            .collect();
+
+        number.trim_end_matches('.').parse::<f32>().ok()
+    }
+
+    fn rating_from_attributes(stars: &Node) -> Option<f32> {
+        RATING_ATTRIBUTES
+            .iter()
+            .filter_map(|attribute| stars.attr(attribute))
+            .find_map(Self::parse_rating_text)
+    }
+
+    /// Counts the icons of widgets drawn as one element per full or half star.
+    fn rating_from_star_count(stars: &Node) -> Option<f32> {
+        let full = stars
+            .find(Class("star-full").or(Class("icon-star")))
+            .count();
+        let half = stars
+            .find(Class("star-half").or(Class("icon-star-half")))
+            .count();
+
+        if full + half == 0 {
+            return None;
+        }
+
+        Some(full as f32 + half as f32 * 0.5)
+    }
+
+    /// Handles widgets drawn as a filled bar, where `width: 90%` means 4.5 stars.
+    fn rating_from_width(stars: &Node) -> Option<f32> {
+        stars
+            .find(Attr("style", ()))
+            .filter_map(|n| n.attr("style"))
+            .filter_map(|style| style.split("width:").nth(1))
+            .filter_map(|width| width.split('%').next())
+            .filter_map(Self::parse_rating_text)
+            .map(|percentage| (percentage / 100.0 * MAX_RATING * 2.0).round() / 2.0)
+            .next()
+    }
+
+    fn validate(rating: f32) -> Result<f32, String> {
+        if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+            return Err(format!(
+                "Rating {} is outside of the {}-{} range",
+                rating, MIN_RATING, MAX_RATING
+            ));
+        }
+
+        Ok(rating)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(html: &str) -> Result<Option<f32>, String> {
+        let widget = SelectorChain::parse(&["span.stars"]).unwrap();
+        RatingExtractor::extract_rating(&Document::from(html), &widget)
+    }
+
+    #[test]
+    fn reads_title_text() {
+        assert_eq!(
+            extract(r#"<span class="stars" title="Nota 4,5"></span>"#),
+            Ok(Some(4.5))
+        );
+        assert_eq!(
+            extract(r#"<span class="stars" title="Nota: 3"></span>"#),
+            Ok(Some(3.0))
+        );
+    }
+
+    #[test]
+    fn prefers_data_attributes() {
+        for attribute in RATING_ATTRIBUTES {
+            let html = format!(
+                r#"<span class="stars" {}="3.5" title="Nota 1"></span>"#,
+                attribute
+            );
+            assert_eq!(extract(&html), Ok(Some(3.5)), "{}", attribute);
+        }
+    }
+
+    #[test]
+    fn counts_star_icons() {
+        assert_eq!(
+            extract(
+                r#"<span class="stars">
+                    <i class="star-full"></i><i class="star-full"></i><i class="star-half"></i>
+                </span>"#
+            ),
+            Ok(Some(2.5))
+        );
+        assert_eq!(
+            extract(
+                r#"<span class="stars"><i class="icon-star"></i><i class="icon-star-half"></i></span>"#
+            ),
+            Ok(Some(1.5))
+        );
+    }
+
+    #[test]
+    fn reads_width_of_filled_bar() {
+        assert_eq!(
+            extract(r#"<span class="stars"><span style="width: 90%"></span></span>"#),
+            Ok(Some(4.5))
+        );
+        assert_eq!(
+            extract(r#"<span class="stars"><span style="color: red; width:41%;"></span></span>"#),
+            Ok(Some(2.0))
+        );
+    }
+
+    #[test]
+    fn treats_missing_widget_as_unrated() {
+        assert_eq!(extract(r#"<span class="title">Alien</span>"#), Ok(None));
+    }
+
+    #[test]
+    fn rejects_ratings_outside_range() {
+        assert!(extract(r#"<span class="stars" title="Nota 9"></span>"#)
+            .unwrap_err()
+            .contains("outside of the 0.5-5 range"));
+        assert!(extract(r#"<span class="stars" data-rating="0"></span>"#).is_err());
+        assert!(extract(r#"<span class="stars"><span style="width: 0%"></span></span>"#).is_err());
+    }
+
+    #[test]
+    fn rejects_widgets_it_cannot_understand() {
+        assert!(extract(r#"<span class="stars" title="Sem nota"></span>"#)
+            .unwrap_err()
+            .starts_with("Could not understand rating widget"));
+    }
+
+    #[test]
+    fn parses_rating_text() {
+        assert_eq!(
+            RatingExtractor::parse_rating_text("4,5 estrelas"),
+            Some(4.5)
+        );
+        assert_eq!(RatingExtractor::parse_rating_text("Nota 4."), Some(4.0));
+        assert_eq!(RatingExtractor::parse_rating_text("Nota"), None);
+    }
+}
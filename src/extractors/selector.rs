@@ -0,0 +1,565 @@
+use std::{convert::TryFrom, fmt};
+
+use select::{document::Document, node::Node, predicate::Any};
+use serde::{Deserialize, Serialize};
+
+/// A CSS-like selector used to find a value in a Filmow page.
+///
+/// Supports tag names, `.class`, `#id`, `[attr]`, `[attr=value]`, `[attr^=value]`,
+/// `[attr*=value]` and the descendant combinator (a space). The value of a match is its trimmed
+/// text, or the value of an attribute when the selector ends with `@attr`, as in
+/// `a[data-movie-pk]@href`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Selector {
+    source: String,
+    compounds: Vec<Compound>,
+    attribute: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<AttributeMatcher>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AttributeMatcher {
+    name: String,
+    operator: AttributeOperator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AttributeOperator {
+    Exists,
+    Equals(String),
+    StartsWith(String),
+    Contains(String),
+}
+
+impl Selector {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let (css, attribute) = match source.rsplit_once('@') {
+            Some((css, attribute)) if !attribute.contains(']') => {
+                (css, Some(attribute.trim().to_string()))
+            }
+            _ => (source, None),
+        };
+        if let Some(attribute) = &attribute {
+            if attribute.is_empty() || !attribute.chars().all(is_name_char) {
+                return Err(format!(
+                    "Invalid selector '{}': invalid attribute name '{}' after '@'",
+                    source, attribute
+                ));
+            }
+        }
+
+        let compounds = split_compounds(css)
+            .iter()
+            .map(|compound| Compound::parse(compound))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid selector '{}': {}", source, e))?;
+
+        if compounds.is_empty() {
+            return Err(format!("Invalid selector '{}': it is empty", source));
+        }
+
+        Ok(Selector {
+            source: source.to_string(),
+            compounds,
+            attribute,
+        })
+    }
+
+    /// Every node of `document` matched by this selector, in document order.
+    pub fn find<'a>(&self, document: &'a Document) -> Vec<Node<'a>> {
+        find_compounds(&self.compounds, document)
+    }
+
+    /// Node nearest to what this selector looks for, showing where it stopped matching: its
+    /// first match, else the first match of its longest leading part, such as the container it
+    /// targets, else the first node sharing an id, class, attribute or tag with its last step.
+    pub fn closest<'a>(&self, document: &'a Document) -> Option<Node<'a>> {
+        let last = self
+            .compounds
+            .last()
+            .expect("Selectors always have at least one compound");
+
+        (1..=self.compounds.len())
+            .rev()
+            .find_map(|len| first_match(&self.compounds[..len], document))
+            .or_else(|| {
+                last.parts()
+                    .into_iter()
+                    .find_map(|part| first_match(&[part], document))
+            })
+    }
+
+    /// Value of a matched node: its trimmed text or the selected attribute.
+    pub fn value_of(&self, node: &Node) -> Option<String> {
+        let value = match &self.attribute {
+            Some(attribute) => node.attr(attribute)?.trim().to_string(),
+            None => node.text().trim().to_string(),
+        };
+
+        Some(value).filter(|v| !v.is_empty())
+    }
+
+    pub fn values(&self, document: &Document) -> Vec<String> {
+        self.find(document)
+            .iter()
+            .filter_map(|node| self.value_of(node))
+            .collect()
+    }
+}
+
+fn find_compounds<'a>(compounds: &[Compound], document: &'a Document) -> Vec<Node<'a>> {
+    let (last, ancestors) = compounds
+        .split_last()
+        .expect("Selectors always have at least one compound");
+
+    document
+        .find(Any)
+        .filter(|node| last.matches(node) && matches_ancestors(node, ancestors))
+        .collect()
+}
+
+fn first_match<'a>(compounds: &[Compound], document: &'a Document) -> Option<Node<'a>> {
+    find_compounds(compounds, document).into_iter().next()
+}
+
+fn matches_ancestors(node: &Node, ancestors: &[Compound]) -> bool {
+    let mut remaining = ancestors.iter().rev().peekable();
+    let mut current = node.parent();
+    while let (Some(compound), Some(ancestor)) = (remaining.peek(), current) {
+        if compound.matches(&ancestor) {
+            remaining.next();
+        }
+        current = ancestor.parent();
+    }
+
+    remaining.peek().is_none()
+}
+
+impl Compound {
+    fn parse(source: &str) -> Result<Self, String> {
+        let mut compound = Compound::default();
+        let mut chars = source.chars().peekable();
+
+        let tag: String = std::iter::from_fn(|| chars.next_if(|c| is_name_char(*c))).collect();
+        if !tag.is_empty() && tag != "*" {
+            compound.tag = Some(tag);
+        }
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => compound.classes.push(read_name(&mut chars)?),
+                '#' => compound.id = Some(read_name(&mut chars)?),
+                '[' => {
+                    let content: String =
+                        std::iter::from_fn(|| chars.next_if(|c| *c != ']')).collect();
+                    if chars.next() != Some(']') {
+                        return Err("unclosed '['".to_string());
+                    }
+                    compound.attributes.push(AttributeMatcher::parse(&content)?);
+                }
+                _ => return Err(format!("unexpected character '{}'", c)),
+            }
+        }
+
+        Ok(compound)
+    }
+
+    /// A compound for each id, class, attribute name and tag this one requires, from the most
+    /// to the least specific.
+    fn parts(&self) -> Vec<Compound> {
+        let mut parts = vec![];
+        if let Some(id) = &self.id {
+            parts.push(Compound {
+                id: Some(id.clone()),
+                ..Compound::default()
+            });
+        }
+        for class in &self.classes {
+            parts.push(Compound {
+                classes: vec![class.clone()],
+                ..Compound::default()
+            });
+        }
+        for attribute in &self.attributes {
+            parts.push(Compound {
+                attributes: vec![AttributeMatcher {
+                    name: attribute.name.clone(),
+                    operator: AttributeOperator::Exists,
+                }],
+                ..Compound::default()
+            });
+        }
+        if let Some(tag) = &self.tag {
+            parts.push(Compound {
+                tag: Some(tag.clone()),
+                ..Compound::default()
+            });
+        }
+        parts
+    }
+
+    fn matches(&self, node: &Node) -> bool {
+        if let Some(tag) = &self.tag {
+            if node.name() != Some(tag.as_str()) {
+                return false;
+            }
+        } else if node.name().is_none() {
+            return false;
+        }
+
+        if let Some(id) = &self.id {
+            if node.attr("id") != Some(id.as_str()) {
+                return false;
+            }
+        }
+
+        let node_classes: Vec<&str> = node
+            .attr("class")
+            .map(|classes| classes.split_whitespace().collect())
+            .unwrap_or_default();
+        if !self
+            .classes
+            .iter()
+            .all(|class| node_classes.contains(&class.as_str()))
+        {
+            return false;
+        }
+
+        self.attributes.iter().all(|matcher| matcher.matches(node))
+    }
+}
+
+impl AttributeMatcher {
+    fn parse(content: &str) -> Result<Self, String> {
+        let unquote = |value: &str| {
+            value
+                .trim()
+                .trim_matches('"')
+                .trim_matches('\'')
+                .to_string()
+        };
+
+        // The first '=' ends the name, so operators inside the value are kept as text.
+        let (name, operator) = match content.split_once('=') {
+            Some((name, value)) => match name.strip_suffix('^') {
+                Some(name) => (name, AttributeOperator::StartsWith(unquote(value))),
+                None => match name.strip_suffix('*') {
+                    Some(name) => (name, AttributeOperator::Contains(unquote(value))),
+                    None => (name, AttributeOperator::Equals(unquote(value))),
+                },
+            },
+            None => (content, AttributeOperator::Exists),
+        };
+
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(format!("invalid attribute name '{}'", name));
+        }
+
+        Ok(AttributeMatcher {
+            name: name.to_string(),
+            operator,
+        })
+    }
+
+    fn matches(&self, node: &Node) -> bool {
+        let value = match node.attr(&self.name) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        match &self.operator {
+            AttributeOperator::Exists => true,
+            AttributeOperator::Equals(expected) => value == expected,
+            AttributeOperator::StartsWith(prefix) => value.starts_with(prefix.as_str()),
+            AttributeOperator::Contains(part) => value.contains(part.as_str()),
+        }
+    }
+}
+
+fn split_compounds(css: &str) -> Vec<String> {
+    let mut compounds = vec![];
+    let mut current = String::new();
+    let mut in_brackets = false;
+    for c in css.chars() {
+        match c {
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            _ => {}
+        }
+
+        if c.is_whitespace() && !in_brackets {
+            if !current.is_empty() {
+                compounds.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() {
+        compounds.push(current);
+    }
+    compounds
+}
+
+fn read_name(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let name: String = std::iter::from_fn(|| chars.next_if(|c| is_name_char(*c))).collect();
+    if name.is_empty() {
+        return Err("expected a name after '.' or '#'".to_string());
+    }
+    Ok(name)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '*' || c == ':'
+}
+
+impl TryFrom<String> for Selector {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Selector::parse(&source)
+    }
+}
+
+impl From<Selector> for String {
+    fn from(selector: Selector) -> Self {
+        selector.source
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Selectors tried in order until one of them finds a value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(transparent)]
+pub struct SelectorChain(pub Vec<Selector>);
+
+impl SelectorChain {
+    pub fn parse(sources: &[&str]) -> Result<Self, String> {
+        sources
+            .iter()
+            .map(|source| Selector::parse(source))
+            .collect::<Result<Vec<_>, _>>()
+            .map(SelectorChain)
+    }
+
+    /// First value found by the first selector that finds any.
+    pub fn first_value(&self, document: &Document) -> Option<String> {
+        self.0
+            .iter()
+            .find_map(|selector| selector.values(document).into_iter().next())
+    }
+
+    /// Every value found by the first selector that finds any.
+    pub fn all_values(&self, document: &Document) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|selector| selector.values(document))
+            .find(|values| !values.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Nodes matched by the first selector that matches any.
+    pub fn find<'a>(&self, document: &'a Document) -> Vec<Node<'a>> {
+        self.0
+            .iter()
+            .map(|selector| selector.find(document))
+            .find(|nodes| !nodes.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Node nearest to what the first selector finding anything close looks for, see
+    /// `Selector::closest`.
+    pub fn closest<'a>(&self, document: &'a Document) -> Option<Node<'a>> {
+        self.0
+            .iter()
+            .find_map(|selector| selector.closest(document))
+    }
+
+    /// Chain trying the selectors of `self` before the ones of `fallback`.
+    pub fn followed_by(&self, fallback: &SelectorChain) -> SelectorChain {
+        let mut selectors = self.0.clone();
+        selectors.extend(fallback.0.iter().cloned());
+        SelectorChain(selectors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><body>
+        <ul class="movies list">
+            <li class="movie" data-movie-pk="300"><a id="dune" href="/duna-t300/" title="Dune @ 2021">Duna</a></li>
+            <li class="movie featured" data-movie-pk="44"><a href="/central-do-brasil-t44/">Central</a></li>
+        </ul>
+        <div class="movie"><a href="/alien-t55/" title="Alien">  Alien  </a></div>
+    </body></html>"#;
+
+    fn values(source: &str) -> Vec<String> {
+        Selector::parse(source)
+            .unwrap()
+            .values(&Document::from(PAGE))
+    }
+
+    fn parse_error(source: &str) -> String {
+        Selector::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn parses_compounds() {
+        let selector = Selector::parse("ul.movies.list li#first[data-movie-pk]@href").unwrap();
+        assert_eq!(selector.attribute.as_deref(), Some("href"));
+        assert_eq!(
+            selector.compounds,
+            vec![
+                Compound {
+                    tag: Some("ul".to_string()),
+                    classes: vec!["movies".to_string(), "list".to_string()],
+                    ..Compound::default()
+                },
+                Compound {
+                    tag: Some("li".to_string()),
+                    id: Some("first".to_string()),
+                    attributes: vec![AttributeMatcher {
+                        name: "data-movie-pk".to_string(),
+                        operator: AttributeOperator::Exists,
+                    }],
+                    ..Compound::default()
+                },
+            ]
+        );
+        assert_eq!(
+            selector.to_string(),
+            "ul.movies.list li#first[data-movie-pk]@href"
+        );
+    }
+
+    #[test]
+    fn parses_attribute_operators() {
+        let operator = |source: &str| {
+            Selector::parse(source).unwrap().compounds[0].attributes[0]
+                .operator
+                .clone()
+        };
+        assert_eq!(
+            operator(r#"[href="/duna-t300/"]"#),
+            AttributeOperator::Equals("/duna-t300/".to_string())
+        );
+        assert_eq!(
+            operator("[href^='/duna']"),
+            AttributeOperator::StartsWith("/duna".to_string())
+        );
+        assert_eq!(
+            operator("[href*=t300]"),
+            AttributeOperator::Contains("t300".to_string())
+        );
+        assert_eq!(
+            operator("[title*=a^=b]"),
+            AttributeOperator::Contains("a^=b".to_string())
+        );
+        assert_eq!(
+            operator("[title^=a*=b]"),
+            AttributeOperator::StartsWith("a*=b".to_string())
+        );
+        assert_eq!(
+            operator("[title=a*=b]"),
+            AttributeOperator::Equals("a*=b".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_at_signs_inside_attribute_values() {
+        let selector = Selector::parse(r#"a[title*="@"]"#).unwrap();
+        assert_eq!(selector.attribute, None);
+        assert_eq!(values(r#"a[title*="@"]"#), vec!["Duna"]);
+        assert_eq!(
+            values(r#"a[title="Dune @ 2021"]@href"#),
+            vec!["/duna-t300/"]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_selectors() {
+        assert!(parse_error("a[href").contains("unclosed '['"));
+        assert!(parse_error(r#"a[title="x y""#).contains("unclosed '['"));
+        assert!(parse_error("li.").contains("expected a name"));
+        assert!(parse_error("#").contains("expected a name"));
+        assert!(parse_error("a[]").contains("invalid attribute name ''"));
+        assert!(parse_error("a[=x]").contains("invalid attribute name ''"));
+        assert!(parse_error("a[ti tle]").contains("invalid attribute name"));
+        assert!(parse_error("a@").contains("after '@'"));
+        assert!(parse_error("a@hr ef").contains("after '@'"));
+        assert!(parse_error("li > a").contains("unexpected character '>'"));
+        assert!(parse_error("").contains("it is empty"));
+        assert!(parse_error("@href").contains("it is empty"));
+    }
+
+    #[test]
+    fn matches_descendants_classes_ids_and_attributes() {
+        assert_eq!(values("ul li a"), vec!["Duna", "Central"]);
+        assert_eq!(values("ul a"), vec!["Duna", "Central"]);
+        assert_eq!(values(".movie a"), vec!["Duna", "Central", "Alien"]);
+        assert_eq!(values("li.movie.featured a"), vec!["Central"]);
+        assert_eq!(values("#dune"), vec!["Duna"]);
+        assert_eq!(
+            values("li[data-movie-pk=44] a@href"),
+            vec!["/central-do-brasil-t44/"]
+        );
+        assert_eq!(values("a[href^=/alien]"), vec!["Alien"]);
+        assert_eq!(values("div.movie li a"), Vec::<String>::new());
+        assert_eq!(values("* a[title]@title"), vec!["Dune @ 2021", "Alien"]);
+    }
+
+    #[test]
+    fn chains_selectors_in_order() {
+        let document = Document::from(PAGE);
+        let chain = SelectorChain::parse(&["span.missing", "li.featured a", "a"]).unwrap();
+        assert_eq!(chain.first_value(&document), Some("Central".to_string()));
+        assert_eq!(chain.all_values(&document), vec!["Central"]);
+
+        let fallback = SelectorChain::parse(&["div a"]).unwrap();
+        let chain = SelectorChain::parse(&["span.missing"])
+            .unwrap()
+            .followed_by(&fallback);
+        assert_eq!(chain.first_value(&document), Some("Alien".to_string()));
+    }
+
+    #[test]
+    fn reads_selectors_from_json() {
+        let chain: SelectorChain = serde_json::from_str(r#"["a@href", "a"]"#).unwrap();
+        assert_eq!(serde_json::to_string(&chain).unwrap(), r#"["a@href","a"]"#);
+        assert!(serde_json::from_str::<SelectorChain>(r#"["a[href"]"#).is_err());
+    }
+
+    #[test]
+    fn finds_closest_node_to_what_selectors_look_for() {
+        let document = Document::from(PAGE);
+        let closest = |source: &str| {
+            Selector::parse(source)
+                .unwrap()
+                .closest(&document)
+                .map(|node| node.name().unwrap_or_default().to_string())
+        };
+
+        assert_eq!(closest("li.movie a"), Some("a".to_string()));
+        assert_eq!(closest("ul.movies table.cast"), Some("ul".to_string()));
+        assert_eq!(closest("span.movie[title]"), Some("li".to_string()));
+        assert_eq!(closest("span[data-movie-pk=1]"), Some("li".to_string()));
+        assert_eq!(closest("table.cast"), None);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::selector::SelectorChain;
+
+/// Version of the selector configuration format understood by this release.
+pub const SELECTORS_VERSION: u32 = 1;
+
+/// Every selector the extractors use to read Filmow pages.
+///
+/// The built-in defaults match the current Filmow layout. A user file, in the JSON format of
+/// this struct, can override any subset of the fields when Filmow changes its markup, without
+/// waiting for a new release: its selectors are tried before the built-in ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SelectorConfig {
+    pub version: u32,
+    /// Original title in a film page.
+    pub title: SelectorChain,
+    pub director: SelectorChain,
+    pub year: SelectorChain,
+    /// Entries of a watched movies listing page, each containing a link and a rating.
+    pub watched_movie_item: SelectorChain,
+    /// Film page url inside a watched movies entry.
+    pub watched_movie_link: SelectorChain,
+    /// Film page urls in a watchlist listing page.
+    pub watchlist_movie_link: SelectorChain,
+    /// Rating widget inside a watched movies entry.
+    pub rating_widget: SelectorChain,
+    /// Links of the paginator of a listing page.
+    pub pagination_link: SelectorChain,
+    /// Link to the page following the current one in a listing page.
+    pub next_page_link: SelectorChain,
+    /// Query parameter holding the page number in pagination links.
+    pub page_parameter: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SelectorOverrides {
+    version: Option<u32>,
+    title: Option<SelectorChain>,
+    director: Option<SelectorChain>,
+    year: Option<SelectorChain>,
+    watched_movie_item: Option<SelectorChain>,
+    watched_movie_link: Option<SelectorChain>,
+    watchlist_movie_link: Option<SelectorChain>,
+    rating_widget: Option<SelectorChain>,
+    pagination_link: Option<SelectorChain>,
+    next_page_link: Option<SelectorChain>,
+    page_parameter: Option<String>,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        let chain = |selectors: &[&str]| {
+            SelectorChain::parse(selectors).expect("Built-in selectors are valid")
+        };
+
+        SelectorConfig {
+            version: SELECTORS_VERSION,
+            title: chain(&["h2.movie-original-title"]),
+            director: chain(&["span[itemprop=director]"]),
+            year: chain(&["small.release"]),
+            watched_movie_item: chain(&["li.movie_list_item[data-movie-pk]"]),
+            watched_movie_link: chain(&["a@href"]),
+            watchlist_movie_link: chain(&["a[data-movie-pk]@href"]),
+            rating_widget: chain(&["span.stars", ".stars"]),
+            pagination_link: chain(&["a[href*=pagina=]@href"]),
+            next_page_link: chain(&["a[rel=next]@href", "link[rel=next]@href", "li.next a@href"]),
+            page_parameter: "pagina".to_string(),
+        }
+    }
+}
+
+impl SelectorConfig {
+    /// Built-in defaults extended with the selectors of the user file at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            format!(
+                "Failed to read selectors file {}. Error: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+        Self::default()
+            .with_overrides(&content)
+            .map_err(|e| format!("Invalid selectors file {}. Error: {}", path.display(), e))
+    }
+
+    pub fn with_overrides(self, overrides_json: &str) -> Result<Self, String> {
+        let overrides: SelectorOverrides =
+            serde_json::from_str(overrides_json).map_err(|e| e.to_string())?;
+
+        if let Some(version) = overrides.version {
+            if version > SELECTORS_VERSION {
+                return Err(format!(
+                    "It was written for selectors version {}, but this release only supports up \
+                     to version {}",
+                    version, SELECTORS_VERSION
+                ));
+            }
+        }
+
+        let merge = |user: Option<SelectorChain>, default: SelectorChain| match user {
+            Some(user) => user.followed_by(&default),
+            None => default,
+        };
+
+        Ok(SelectorConfig {
+            version: SELECTORS_VERSION,
+            title: merge(overrides.title, self.title),
+            director: merge(overrides.director, self.director),
+            year: merge(overrides.year, self.year),
+            watched_movie_item: merge(overrides.watched_movie_item, self.watched_movie_item),
+            watched_movie_link: merge(overrides.watched_movie_link, self.watched_movie_link),
+            watchlist_movie_link: merge(overrides.watchlist_movie_link, self.watchlist_movie_link),
+            rating_widget: merge(overrides.rating_widget, self.rating_widget),
+            pagination_link: merge(overrides.pagination_link, self.pagination_link),
+            next_page_link: merge(overrides.next_page_link, self.next_page_link),
+            page_parameter: overrides.page_parameter.unwrap_or(self.page_parameter),
+        })
+    }
+}
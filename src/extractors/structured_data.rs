@@ -0,0 +1,158 @@
+use std::fmt;
+
+use select::{
+    document::Document,
+    predicate::{Attr, Name, Predicate},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Where the value of an extracted field came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldSource {
+    /// The visible markup, read through the configured selectors.
+    Html,
+    /// A schema.org `Movie` embedded as JSON-LD.
+    JsonLd,
+    /// OpenGraph and other meta tags.
+    MetaTags,
+}
+
+impl fmt::Display for FieldSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldSource::Html => write!(f, "html"),
+            FieldSource::JsonLd => write!(f, "json-ld"),
+            FieldSource::MetaTags => write!(f, "meta tags"),
+        }
+    }
+}
+
+/// Film details read from the machine readable data of a page rather than its visible markup.
+#[derive(Debug, Default)]
+pub struct StructuredData {
+    pub title: Option<String>,
+    pub director: Option<String>,
+    pub year: Option<u32>,
+}
+
+impl StructuredData {
+    pub fn from_json_ld(document: &Document) -> Self {
+        let movie = document
+            .find(Name("script").and(Attr("type", "application/ld+json")))
+            .filter_map(|n| serde_json::from_str::<Value>(&n.text()).ok())
+            .find_map(|json| find_movie(&json).cloned());
+
+        let movie = match movie {
+            Some(movie) => movie,
+            None => return Self::default(),
+        };
+
+        StructuredData {
+            title: movie["name"].as_str().map(clean),
+            director: person_names(&movie["director"]),
+            year: ["datePublished", "dateCreated", "copyrightYear"]
+                .iter()
+                .find_map(|field| match &movie[field] {
+                    Value::Number(year) => year.as_u64().map(|y| y as u32),
+                    Value::String(date) => year_from(date),
+                    _ => None,
+                }),
+        }
+    }
+
+    pub fn from_meta_tags(document: &Document) -> Self {
+        let meta = |property: &str| {
+            document
+                .find(Name("meta").and(Attr("property", property).or(Attr("name", property))))
+                .filter_map(|n| n.attr("content"))
+                .map(clean)
+                .find(|content| !content.is_empty())
+        };
+
+        let og_title = meta("og:title").map(|title| strip_site_name(&title));
+        let title_year = og_title.as_deref().and_then(trailing_year);
+
+        StructuredData {
+            title: og_title.map(|title| strip_trailing_year(&title)),
+            director: meta("video:director")
+                .filter(|director| !director.starts_with("http"))
+                .or_else(|| meta("director")),
+            year: meta("video:release_date")
+                .as_deref()
+                .and_then(year_from)
+                .or(title_year),
+        }
+    }
+}
+
+/// Finds the first schema.org `Movie` in a JSON-LD value, which can be a list or a `@graph`.
+fn find_movie(json: &Value) -> Option<&Value> {
+    match json {
+        Value::Array(items) => items.iter().find_map(find_movie),
+        Value::Object(object) => {
+            let is_movie = match &object.get("@type") {
+                Some(Value::String(t)) => t == "Movie",
+                Some(Value::Array(types)) => types.iter().any(|t| t == "Movie"),
+                _ => false,
+            };
+            if is_movie {
+                return Some(json);
+            }
+            object.get("@graph").and_then(find_movie)
+        }
+        _ => None,
+    }
+}
+
+fn person_names(value: &Value) -> Option<String> {
+    let names: Vec<String> = match value {
+        Value::String(name) => vec![clean(name)],
+        Value::Object(person) => person
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(|n| vec![clean(n)])
+            .unwrap_or_default(),
+        Value::Array(people) => people.iter().filter_map(person_names).collect(),
+        _ => vec![],
+    };
+
+    Some(names.join(", ")).filter(|names| !names.is_empty())
+}
+
+fn year_from(date: &str) -> Option<u32> {
+    date.trim()
+        .get(..4)
+        .and_then(|year| year.parse::<u32>().ok())
+        .filter(|year| *year > 1800)
+}
+
+/// Year of titles shaped like "The Godfather (1972)".
+fn trailing_year(title: &str) -> Option<u32> {
+    let (_, year) = title.trim_end().strip_suffix(')')?.rsplit_once('(')?;
+    year_from(year).filter(|_| year.trim().len() == 4)
+}
+
+fn strip_trailing_year(title: &str) -> String {
+    match trailing_year(title) {
+        Some(_) => title
+            .rsplit_once('(')
+            .map(|(title, _)| title.trim().to_string())
+            .unwrap_or_else(|| title.to_string()),
+        None => title.to_string(),
+    }
+}
+
+fn strip_site_name(title: &str) -> String {
+    title
+        .strip_suffix("- Filmow")
+        .or_else(|| title.strip_suffix("| Filmow"))
+        .unwrap_or(title)
+        .trim()
+        .to_string()
+}
+
+fn clean(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
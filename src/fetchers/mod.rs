@@ -1,2 +1,3 @@
+pub mod paginator;
 pub mod watched_list_fetcher;
 pub mod watchlist_fetcher;
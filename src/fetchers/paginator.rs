@@ -0,0 +1,92 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    clients::filmow_client::FilmowClient, model::collection::Collection,
+    progress::CollectionProgress,
+};
+
+pub struct ListingPage {
+    pub number: i32,
+    pub html: String,
+}
+
+/// Walks the listing pages of a collection one at a time, following the next link of each page
+/// and otherwise moving on while its paginator shows later pages.
+pub struct Paginator {
+    client: Arc<FilmowClient>,
+    collection: Collection,
+    user: Arc<String>,
+    progress: Arc<CollectionProgress>,
+    next: Option<i32>,
+    highest_known: i32,
+    visited: HashSet<i32>,
+}
+
+impl Paginator {
+    pub fn new(
+        client: Arc<FilmowClient>,
+        collection: Collection,
+        user: Arc<String>,
+        progress: Arc<CollectionProgress>,
+    ) -> Self {
+        Paginator {
+            client,
+            collection,
+            user,
+            progress,
+            next: Some(1),
+            highest_known: 1,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Fetches the next listing page, skipping pages that can't be fetched when later ones are
+    /// known to exist. Pages are reported as discovered as soon as they are known, before they
+    /// are fetched.
+    pub async fn next_page(&mut self) -> Option<ListingPage> {
+        while let Some(number) = self.next.take() {
+            if !self.visited.insert(number) {
+                log::warn!(
+                    "Pagination of {} leads back to page {}, stopping there",
+                    self.collection,
+                    number
+                );
+                return None;
+            }
+            // The first page is known to exist before anything is fetched.
+            self.progress.pages_discovered(number as usize);
+
+            let url = self
+                .client
+                .urls()
+                .collection_page(self.collection, &self.user, number);
+            match self.client.get_html_from_url(&url).await {
+                Ok(html) => {
+                    let pagination = self.client.extractor().get_pagination(&html);
+                    self.highest_known = self
+                        .highest_known
+                        .max(pagination.highest_page.unwrap_or(number))
+                        .max(pagination.next_page.unwrap_or(number));
+                    self.progress.pages_discovered(self.highest_known as usize);
+                    self.next = pagination
+                        .next_page
+                        .or_else(|| (self.highest_known > number).then_some(number + 1));
+                    return Some(ListingPage { number, html });
+                }
+                Err(e) => {
+                    log::error!(
+                        "Failed to get {} page {} at {}. Error: {}",
+                        self.collection,
+                        number,
+                        url,
+                        e
+                    );
+                    self.progress.page_failed(&url, &e);
+                    self.next = (self.highest_known > number).then_some(number + 1);
+                }
+            }
+        }
+
+        None
+    }
+}
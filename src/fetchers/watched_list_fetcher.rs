@@ -1,107 +1,68 @@
 use std::sync::Arc;
 
+use tokio::sync::mpsc::Sender;
+
 use crate::{
-    clients::filmow_client::FilmowClient, extractors::movie_extractor::MovieExtractor,
-    model::movie::Movie,
+    clients::filmow_client::{FilmowClient, PreliminaryMovieInformation},
+    fetchers::paginator::Paginator,
+    model::collection::Collection,
+    progress::CollectionProgress,
 };
 
 #[derive(Clone)]
 pub struct WatchedMoviesFetcher {
     filmow_client: Arc<FilmowClient>,
+    progress: Arc<CollectionProgress>,
 }
 
 impl WatchedMoviesFetcher {
-    pub fn new(filmow_client: Arc<FilmowClient>) -> Self {
-        WatchedMoviesFetcher { filmow_client }
+    pub fn new(filmow_client: Arc<FilmowClient>, progress: Arc<CollectionProgress>) -> Self {
+        WatchedMoviesFetcher {
+            filmow_client,
+            progress,
+        }
     }
 
-    pub async fn get_all_watched_movies(
+    pub async fn discover_watched_movies(
         shared_self: Arc<WatchedMoviesFetcher>,
         user: Arc<String>,
-    ) -> Vec<Movie> {
+        sender: Sender<PreliminaryMovieInformation>,
+    ) {
         log::info!("Fetching watched movies for user {}", user);
 
-        let number_of_pages = shared_self.get_last_watched_page_number(user.clone()).await;
-        log::info!("Number of watched movies pages {:?}", number_of_pages);
-
-        let mut resp = vec![];
-        let mut handles = vec![];
-        for page_num in 1..=number_of_pages {
-            let self_clone = shared_self.clone();
-            let user_clone = user.clone();
-
-            let page_movies_handle = tokio::spawn(async move {
-                self_clone
-                    .get_all_movies_for_watched_page(page_num, user_clone)
-                    .await
-            });
-            handles.push(page_movies_handle)
-        }
+        let mut paginator = Paginator::new(
+            shared_self.filmow_client.clone(),
+            Collection::Watched,
+            user,
+            shared_self.progress.clone(),
+        );
+        while let Some(page) = paginator.next_page().await {
+            shared_self.progress.page_fetched(page.number as usize);
 
-        for handle in handles {
-            let mut movies = handle.await.unwrap();
-            resp.append(&mut movies);
-        }
-        resp
-    }
-
-    pub async fn get_all_movies_for_watched_page(
-        &self,
-        page_num: i32,
-        user: Arc<String>,
-    ) -> Vec<Movie> {
-        let watched_url_for_page = Self::get_watched_url_for_page(user, page_num);
-        match self
-            .filmow_client
-            .get_html_from_url(watched_url_for_page.as_str())
-            .await
-        {
-            Ok(watched_page_html) => {
-                let preliminary_movies_info =
-                    MovieExtractor::get_preliminary_info_for_watched_movies(
-                        watched_page_html.as_str(),
-                    );
-                let page_movies = FilmowClient::parallel_build_movie_from_preliminary_info(
-                    self.filmow_client.clone(),
-                    preliminary_movies_info,
-                )
-                .await;
-                log::info!("Movies for watched page {}: {:?}", page_num, page_movies);
-                page_movies
-            }
-            Err(e) => {
-                log::error!(
-                    "Failed to get html for url {}. Error: {}",
-                    watched_url_for_page,
-                    e
+            let preliminary_movies_info = shared_self
+                .filmow_client
+                .extractor()
+                .get_preliminary_info_for_watched_movies(
+                    page.html.as_str(),
+                    shared_self.filmow_client.urls(),
+                );
+            if preliminary_movies_info.is_empty() {
+                log::info!(
+                    "Watched movies page {} has no movies, stopping",
+                    page.number
                 );
-                vec![]
+                break;
             }
-        }
-    }
 
-    async fn get_last_watched_page_number(&self, user: Arc<String>) -> i32 {
-        log::info!("Getting total number of watched pages");
-        let watched_url = Self::get_watched_url_for_page(user, 1);
-        match self
-            .filmow_client
-            .get_html_from_url(watched_url.as_str())
-            .await
-        {
-            Ok(watched_page_html) => {
-                MovieExtractor::get_last_page_from_html(watched_page_html.as_str()).unwrap_or(1)
-            }
-            Err(e) => {
-                panic!("Error when trying to find number of watched pages: {}", e);
+            shared_self
+                .progress
+                .films_discovered(preliminary_movies_info.len());
+            for info in preliminary_movies_info {
+                if sender.send(info).await.is_err() {
+                    // Nobody is resolving films anymore, so the rest of the pages are moot.
+                    return;
+                }
             }
         }
     }
-
-    fn get_watched_url_for_page(user: Arc<String>, page: i32) -> String {
-        if page == 1 {
-            return format!("https://filmow.com/usuario/{}/ja-vi/", user);
-        }
-
-        format!("https://filmow.com/usuario/{}/ja-vi/?pagina={}", user, page)
-    }
 }
@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path},
+};
+
+use csv::ReaderBuilder;
+use zip::ZipArchive;
+
+use crate::model::{collection::Collection, movie::Movie};
+
+/// Files of a Letterboxd data export listing films already logged as watched.
+const WATCHED_FILES: [&str; 3] = ["watched.csv", "ratings.csv", "diary.csv"];
+/// Holds past ratings of diary entries rather than the current rating of each film.
+const DIARY_FILE: &str = "diary.csv";
+const WATCHLIST_FILE: &str = "watchlist.csv";
+/// Folders of a Letterboxd data export holding entries removed from the profile.
+const REMOVED_FOLDERS: [&str; 2] = ["deleted", "orphaned"];
+/// Years apart a film can be listed on both sites and still be taken as the same film, since
+/// they often disagree on release years.
+pub const YEAR_TOLERANCE: u32 = 1;
+
+/// A film as listed in a Letterboxd data export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetterboxdFilm {
+    pub title: String,
+    pub year: u32,
+    /// Rating from 0.5 to 5 stars, when rated.
+    pub rating: Option<f32>,
+}
+
+/// Films already logged on Letterboxd, read from its data export. Films are matched by
+/// title and year, up to `YEAR_TOLERANCE` apart, since neither site exports the ids of the
+/// other.
+#[derive(Debug, Clone, Default)]
+pub struct LetterboxdExport {
+    /// Films of every collection keyed by `normalize_title`.
+    watched: HashMap<String, Vec<LetterboxdFilm>>,
+    watchlist: HashMap<String, Vec<LetterboxdFilm>>,
+}
+
+impl LetterboxdExport {
+    /// Reads the export at `path`: the ZIP downloaded from Letterboxd, the folder it was
+    /// extracted to or one of its CSV files, which is taken as the watchlist when named
+    /// `watchlist.csv` and as watched films otherwise.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut export = LetterboxdExport::default();
+
+        if path.is_dir() {
+            for file_name in WATCHED_FILES.iter().chain([&WATCHLIST_FILE]) {
+                let file_path = path.join(file_name);
+                if file_path.exists() {
+                    let content = fs::read_to_string(&file_path).map_err(|e| {
+                        format!("Failed to read {}. Error: {}", file_path.display(), e)
+                    })?;
+                    export.add_csv(file_name, &content)?;
+                }
+            }
+        } else if has_extension(path, "zip") {
+            export.add_zip(path)?;
+        } else {
+            let content = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}. Error: {}", path.display(), e))?;
+            let file_name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            export.add_csv(file_name, &content)?;
+        }
+
+        log::info!(
+            "Read {} watched films and {} watchlist films from Letterboxd export {}",
+            export.watched.values().map(Vec::len).sum::<usize>(),
+            export.watchlist.values().map(Vec::len).sum::<usize>(),
+            path.display()
+        );
+        Ok(export)
+    }
+
+    /// Whether `movie` is already in the Letterboxd counterpart of `collection`.
+    pub fn contains(&self, collection: Collection, movie: &Movie) -> bool {
+        self.find(collection, &movie.title, movie.year).is_some()
+    }
+
+    /// The film matching `title` and `year` in the Letterboxd counterpart of `collection`,
+    /// preferring one listed under the same year over one up to `YEAR_TOLERANCE` apart.
+    /// Watchlist films are also looked for among watched films, since watching a film takes it
+    /// off the Letterboxd watchlist.
+    pub fn find(&self, collection: Collection, title: &str, year: u32) -> Option<&LetterboxdFilm> {
+        let searched = match collection {
+            Collection::Watched => vec![Collection::Watched],
+            Collection::Watchlist => vec![Collection::Watchlist, Collection::Watched],
+        };
+        let find_in = |same_year: &dyn Fn(u32) -> bool| {
+            searched.iter().find_map(|searched| {
+                self.with_title(*searched, title)
+                    .find(|film| same_year(film.year))
+            })
+        };
+        find_in(&|found| found == year)
+            .or_else(|| find_in(&|found| found.abs_diff(year) <= YEAR_TOLERANCE))
+    }
+
+    /// Every film with `title` in the Letterboxd counterpart of `collection`, whatever its year.
+    fn with_title<'a>(
+        &'a self,
+        collection: Collection,
+        title: &str,
+    ) -> impl Iterator<Item = &'a LetterboxdFilm> {
+        self.films(collection)
+            .get(&normalize_title(title))
+            .into_iter()
+            .flatten()
+    }
+
+    fn films(&self, collection: Collection) -> &HashMap<String, Vec<LetterboxdFilm>> {
+        match collection {
+            Collection::Watched => &self.watched,
+            Collection::Watchlist => &self.watchlist,
+        }
+    }
+
+    fn add_zip(&mut self, path: &Path) -> Result<(), String> {
+        let file = File::open(path)
+            .map_err(|e| format!("Failed to open {}. Error: {}", path.display(), e))?;
+        let mut archive = ZipArchive::new(file)
+            .map_err(|e| format!("Invalid ZIP file {}. Error: {}", path.display(), e))?;
+
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index).map_err(|e| {
+                format!(
+                    "Failed to read {} from {}. Error: {}",
+                    index,
+                    path.display(),
+                    e
+                )
+            })?;
+            let Some(entry_path) = entry.enclosed_name().map(Path::to_path_buf) else {
+                continue;
+            };
+            let removed = entry_path.components().any(|component| {
+                matches!(component, Component::Normal(name) if REMOVED_FOLDERS.iter().any(|folder| name == *folder))
+            });
+            let file_name = entry_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_string();
+            if removed || !is_export_file(&file_name) {
+                continue;
+            }
+
+            let mut content = String::new();
+            entry.read_to_string(&mut content).map_err(|e| {
+                format!(
+                    "Failed to read {} from {}. Error: {}",
+                    entry_path.display(),
+                    path.display(),
+                    e
+                )
+            })?;
+            self.add_csv(&file_name, &content)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the films of a CSV file, in the layout of the Letterboxd export (`Name`) or of
+    /// its importer (`Title`).
+    fn add_csv(&mut self, file_name: &str, content: &str) -> Result<(), String> {
+        let mut rdr = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(content.as_bytes());
+        let header = rdr
+            .headers()
+            .map_err(|e| format!("Invalid CSV file {}. Error: {}", file_name, e))?
+            .clone();
+        let column = |names: &[&str]| {
+            header.iter().position(|column| {
+                names
+                    .iter()
+                    .any(|name| column.trim().eq_ignore_ascii_case(name))
+            })
+        };
+        let (Some(title_column), Some(year_column)) =
+            (column(&["Name", "Title"]), column(&["Year"]))
+        else {
+            return Err(format!(
+                "CSV file {} has no Name or Title and Year columns",
+                file_name
+            ));
+        };
+        let rating_column = if file_name.eq_ignore_ascii_case(DIARY_FILE) {
+            None
+        } else {
+            column(&["Rating"])
+        };
+
+        let films = if file_name.eq_ignore_ascii_case(WATCHLIST_FILE) {
+            &mut self.watchlist
+        } else {
+            &mut self.watched
+        };
+        for record in rdr.records() {
+            let record =
+                record.map_err(|e| format!("Invalid CSV file {}. Error: {}", file_name, e))?;
+            let title = record.get(title_column).unwrap_or_default().trim();
+            let rating = rating_column
+                .and_then(|column| record.get(column))
+                .and_then(|rating| rating.trim().parse::<f32>().ok());
+            match record
+                .get(year_column)
+                .map(|year| year.trim().parse::<u32>())
+            {
+                Some(Ok(year)) if !title.is_empty() => {
+                    let same_title = films.entry(normalize_title(title)).or_default();
+                    match same_title.iter_mut().find(|film| film.year == year) {
+                        Some(film) => film.rating = rating.or(film.rating),
+                        None => same_title.push(LetterboxdFilm {
+                            title: title.to_string(),
+                            year,
+                            rating,
+                        }),
+                    }
+                }
+                _ => log::warn!(
+                    "Ignoring entry of {} without a title and year: {:?}",
+                    file_name,
+                    record
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_export_file(file_name: &str) -> bool {
+    WATCHED_FILES
+        .iter()
+        .chain([&WATCHLIST_FILE])
+        .any(|name| file_name.eq_ignore_ascii_case(name))
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Lowercases `title` and keeps only its letters and digits, so punctuation and spacing
+/// differences between both sites don't matter.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
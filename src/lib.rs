@@ -1,65 +1,490 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Instant};
+
+use tokio::sync::mpsc;
 
 mod clients;
-use clients::filmow_client::FilmowClient;
+pub use clients::{
+    cassette::{RecordingTransport, ReplayTransport},
+    transport::{FixtureTransport, HttpResponse, HttpTransport, ReqwestTransport},
+};
+use clients::{
+    filmow_client::FilmowClient, filmow_urls::FilmowUrls, local_pages::LocalPageStore,
+    page_archive::PageArchive,
+};
+
+mod config;
+pub use config::RunConfig;
+
+mod builder;
+pub use builder::{ExportBuilder, FetchedCollection};
+
+mod doctor;
+pub use doctor::{CheckResult, CheckStatus, DoctorReport};
+
+mod validate;
+pub use validate::{validate_import_file, Diagnostic, Severity, ValidationReport};
+
+mod reconcile;
+pub use reconcile::{
+    reconcile, CollectionReconciliation, RatingMismatch, ReconciliationReport, YearMismatch,
+};
+
+mod stats;
+pub use stats::{CollectionStats, DirectorCount, ProfileStats, RatingCount};
+
+mod diff;
+pub use diff::{diff_exports, ExportDiff, FilmChange};
+
+mod exported_files;
+pub use exported_files::ExportedFilm;
+
+mod letterboxd_export;
+use letterboxd_export::LetterboxdExport;
 
 mod extractors;
+use extractors::{movie_extractor::MovieExtractor, selector_config::SelectorConfig};
 mod model;
+pub use model::collection::Collection;
+
+pub use model::movie::Movie;
 
 mod persisters;
-use persisters::csv_writer::CsvWriter;
+use persisters::exporter::ExporterRegistry;
+pub use persisters::exporter::{ExportSink, Exporter};
+pub use persisters::letterboxd_csv_exporter::ChunkLimits;
+
+mod transformers;
+pub use transformers::deduplicator::{
+    DeduplicationReport, OverlapPolicy, RemovalReason, RemovedFilm,
+};
+pub use transformers::rating_transformer::{RatingOptions, RatingScale, RoundingMode};
+use transformers::{deduplicator::Deduplicator, rating_transformer::RatingTransformer};
 
 mod fetchers;
 
-pub async fn run(user: String) {
-    let filmow_client = Arc::new(FilmowClient::new());
+/// Movies fetched but not yet written by the exporters.
+const MOVIE_BUFFER: usize = 64;
+
+mod events;
+pub use events::{ExportEvent, ExportObserver};
+
+mod report;
+pub use report::{CollectionReport, ExportReport, ExportStatus, Failure};
+
+mod progress;
+use progress::{CollectionProgress, ProgressReporter};
+
+/// Built-in selectors, in the format accepted by `RunConfig::selectors_file`.
+pub fn default_selectors_json() -> String {
+    serde_json::to_string_pretty(&SelectorConfig::default())
+        .expect("Built-in selectors are serializable")
+}
+
+/// Checks that the extractors still understand the pages of `profile` and of up to `max_films`
+/// of its films, reading them through the same sources a run with `config` would.
+pub async fn run_doctor(
+    profile: String,
+    max_films: usize,
+    config: RunConfig,
+) -> Result<DoctorReport, String> {
+    let transport = build_transport(&config)?;
+    let client = build_client(&config, transport, None)?;
+    Ok(doctor::diagnose(&client, &profile, max_films).await)
+}
+
+/// Fetches every collection of `user` as a run with `config` would, without writing any file,
+/// and summarizes the fetched movies. Ratings are kept as Filmow shows them, whatever the rating
+/// options of `config`.
+pub async fn run_stats(user: String, config: RunConfig) -> Result<ProfileStats, String> {
+    let config = RunConfig {
+        rating: RatingOptions::default(),
+        ..config
+    };
+    let fetched = fetch_movies(user.clone(), config).await?;
+    Ok(ProfileStats::compute(&user, &fetched))
+}
+
+/// Fetches the collections of `user` selected in `config` and returns their movies as they would
+/// be exported, with ratings converted and films already on Letterboxd left out, instead of
+/// writing any file. Exporter, output, archive and recording options of `config` are ignored.
+pub async fn fetch_movies(
+    user: String,
+    config: RunConfig,
+) -> Result<Vec<FetchedCollection>, String> {
+    let transport = build_transport(&config)?;
+    let client = Arc::new(build_client(&config, transport, None)?);
+    let deduplicator = Arc::new(build_deduplicator(&config)?);
+    let rating_transformer = Arc::new(RatingTransformer::new(config.rating.clone()));
     let user = Arc::new(user);
 
-    let movies_handle = tokio::spawn(fetch_and_save_movies(filmow_client.clone(), user.clone()));
-    let watchlist_handle = tokio::spawn(fetch_and_save_watchlist(
-        filmow_client.clone(),
-        user.clone(),
-    ));
+    for_each_collection(&config, |progress| {
+        collect_movies(
+            client.clone(),
+            user.clone(),
+            progress,
+            deduplicator.clone(),
+            rating_transformer.clone(),
+        )
+    })
+    .await
+}
 
-    movies_handle
-        .await
-        .expect("Error while fetching watched movie list");
-    watchlist_handle
-        .await
-        .expect("Error while fetching watchlist");
+/// Fetches the collection followed by `progress` and keeps the movies that would be exported.
+async fn collect_movies(
+    client: Arc<FilmowClient>,
+    user: Arc<String>,
+    progress: Arc<CollectionProgress>,
+    deduplicator: Arc<Deduplicator>,
+    rating_transformer: Arc<RatingTransformer>,
+) -> Result<FetchedCollection, String> {
+    let mut movies = vec![];
+    stream_exported_movies(
+        client,
+        user,
+        progress.clone(),
+        deduplicator,
+        &rating_transformer,
+        |movie| movies.push(movie),
+    )
+    .await?;
+
+    Ok(FetchedCollection {
+        collection: progress.collection(),
+        movies,
+        failed: progress.failed_films().len(),
+        fetched_pages: progress.fetched_pages(),
+        failed_pages: progress.failed_pages().len(),
+    })
+}
+
+/// Exports every collection of `user` with the default configuration.
+pub async fn run(user: String) -> Result<ExportReport, String> {
+    run_with_config(user, RunConfig::default()).await
+}
+
+/// Exports the collections of `user` selected in `config`. Errors that stop the run, such as an
+/// unknown exporter or a crash while fetching, are returned as `Err`, while the report tells
+/// whether every film made it to the files.
+pub async fn run_with_config(user: String, config: RunConfig) -> Result<ExportReport, String> {
+    let started = Instant::now();
+    let exporters = Arc::new(ExporterRegistry::with_defaults(&config).resolve(&config.exporters)?);
+    let archive = match &config.archive_dir {
+        Some(archive_dir) => Some(Arc::new(PageArchive::open(archive_dir)?)),
+        None => None,
+    };
+    let transport = build_transport(&config)?;
+    let recorder = config
+        .record_cassette
+        .as_ref()
+        .map(|cassette| Arc::new(RecordingTransport::new(transport.clone(), cassette)));
+    let transport = match &recorder {
+        Some(recorder) => recorder.clone(),
+        None => transport,
+    };
+    let filmow_client = Arc::new(build_client(&config, transport, archive.clone())?);
+    let deduplicator = Arc::new(build_deduplicator(&config)?);
+    let config = Arc::new(config);
+    let user = Arc::new(user);
+
+    let collections = for_each_collection(&config, |progress| {
+        fetch_and_export(
+            filmow_client.clone(),
+            user.clone(),
+            progress.collection(),
+            progress,
+            deduplicator.clone(),
+            exporters.clone(),
+            config.clone(),
+        )
+    })
+    .await;
+
+    // The pages fetched so far are kept even when the run failed, as that's when they're most
+    // useful to look into or replay.
+    let mut errors = vec![];
+    if let Some(archive) = archive {
+        match archive.save_index() {
+            Ok(index_path) => log::info!("Saved archive index to {}", index_path.display()),
+            Err(e) => {
+                log::error!("{}", e);
+                errors.push(e);
+            }
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        match recorder.save() {
+            Ok(cassette) => log::info!("Saved recorded requests to {}", cassette.display()),
+            Err(e) => {
+                log::error!("{}", e);
+                errors.push(e);
+            }
+        }
+    }
+    let collections = collections?;
+
+    let deduplication = deduplicator.report();
+    if !deduplication.removed.is_empty() || !deduplication.overlapping.is_empty() {
+        log::info!("{}", deduplication);
+    }
 
     log::info!(
         "Filmow2letterboxed has finished importing your Filmow profile! \
-         You should be able to find .csv files in the same directory of the executable. \
+         You should be able to find the exported files in {}. \
          For more instructions on how to import these files to letterboxd, \
-         go to https://github.com/LucasIME/filmow2letterboxd"
+         go to https://github.com/LucasIME/filmow2letterboxd",
+        config.output_dir.display()
     );
+
+    Ok(ExportReport {
+        collections,
+        deduplication,
+        errors,
+        duration: started.elapsed(),
+    })
+}
+
+/// Runs `task` concurrently for every collection of `config`, showing their progress when asked
+/// to. Fails when any of them fails or crashes.
+async fn for_each_collection<T, F, Fut>(config: &RunConfig, task: F) -> Result<Vec<T>, String>
+where
+    F: Fn(Arc<CollectionProgress>) -> Fut,
+    Fut: Future<Output = Result<T, String>> + Send + 'static,
+    T: Send + 'static,
+{
+    let progress: Vec<_> = config
+        .collections
+        .iter()
+        .map(|collection| {
+            Arc::new(CollectionProgress::new(
+                *collection,
+                config.observers.clone(),
+            ))
+        })
+        .collect();
+    let reporter = config
+        .show_progress
+        .then(|| ProgressReporter::start(progress.clone()));
+
+    let handles: Vec<_> = progress
+        .into_iter()
+        .map(|progress| (progress.collection(), tokio::spawn(task(progress))))
+        .collect();
+
+    let mut results = vec![];
+    for (collection, handle) in handles {
+        results.push(
+            handle
+                .await
+                .map_err(|e| format!("Error while fetching {}: {:?}", collection, e))
+                .and_then(|result| result),
+        );
+    }
+
+    if let Some(reporter) = reporter {
+        reporter.finish().await;
+    }
+    results.into_iter().collect()
 }
 
-async fn fetch_and_save_movies(client: Arc<FilmowClient>, user: Arc<String>) {
-    let watched_movies_file_name = "watched.csv";
-    let mut watched_movies = FilmowClient::get_all_watched_movies(client, user).await;
-    watched_movies.sort_by_key(|movie| movie.title.clone());
-
-    match CsvWriter::save_movies_to_csv(watched_movies, watched_movies_file_name) {
-        Err(e) => return log::error!("Error when saving watched movies: {:?}", e),
-        _ => log::info!(
-            "Successfully generated watched movies file: {}",
-            watched_movies_file_name
-        ),
+fn build_transport(config: &RunConfig) -> Result<Arc<dyn HttpTransport>, String> {
+    if let Some(cassette) = &config.replay_cassette {
+        return Ok(Arc::new(ReplayTransport::load(cassette)?));
+    }
+
+    if let Some(pages_dir) = &config.offline_dir {
+        return Ok(Arc::new(LocalPageStore::open(pages_dir)?));
     }
+
+    Ok(config
+        .transport
+        .clone()
+        .unwrap_or_else(|| Arc::new(ReqwestTransport::new())))
 }
 
-async fn fetch_and_save_watchlist(client: Arc<FilmowClient>, user: Arc<String>) {
-    let watchlist_file_name = "watchlist.csv";
-    let mut watchlist_movies = FilmowClient::get_all_movies_from_watchlist(client, user).await;
-    watchlist_movies.sort_by_key(|movie| movie.title.clone());
-
-    match CsvWriter::save_movies_to_csv(watchlist_movies, watchlist_file_name) {
-        Err(e) => return log::error!("Error when saving watchlist: {:?}", e),
-        _ => log::info!(
-            "Successfully generated watchlist file: {}",
-            watchlist_file_name
-        ),
+/// Deduplicator of a run with `config`, which doesn't wait for watched films when they aren't
+/// fetched.
+fn build_deduplicator(config: &RunConfig) -> Result<Deduplicator, String> {
+    let mut deduplicator = Deduplicator::new(config.overlap_policy);
+    if let Some(path) = &config.letterboxd_export {
+        deduplicator = deduplicator.with_letterboxd_export(LetterboxdExport::load(path)?);
+    }
+    if !config.collections.contains(&Collection::Watched) {
+        deduplicator.resolution_complete(Collection::Watched);
     }
+    Ok(deduplicator)
+}
+
+fn build_client(
+    config: &RunConfig,
+    transport: Arc<dyn HttpTransport>,
+    archive: Option<Arc<PageArchive>>,
+) -> Result<FilmowClient, String> {
+    let mut client =
+        FilmowClient::with_transport(transport).with_urls(FilmowUrls::new(&config.base_url)?);
+    if let Some(selectors_file) = &config.selectors_file {
+        client = client.with_extractor(MovieExtractor::new(SelectorConfig::load(selectors_file)?));
+    }
+
+    Ok(match archive {
+        Some(archive) => client.with_archive(archive),
+        None => client,
+    })
+}
+
+/// Fetches the collection followed by `progress` and passes every movie it would export, with
+/// its rating transformed, to `export`. Returns the films left out because they are already on
+/// Letterboxd.
+async fn stream_exported_movies(
+    client: Arc<FilmowClient>,
+    user: Arc<String>,
+    progress: Arc<CollectionProgress>,
+    deduplicator: Arc<Deduplicator>,
+    rating_transformer: &RatingTransformer,
+    mut export: impl FnMut(Movie),
+) -> Result<Vec<String>, String> {
+    let collection = progress.collection();
+    let (sender, mut receiver) = mpsc::channel(MOVIE_BUFFER);
+    let fetching = tokio::spawn(FilmowClient::stream_collection(
+        client,
+        collection,
+        user,
+        progress,
+        deduplicator.clone(),
+        sender,
+    ));
+
+    let mut already_on_letterboxd = vec![];
+    while let Some(movie) = receiver.recv().await {
+        if deduplicator.already_on_letterboxd(collection, &movie) {
+            log::debug!(
+                "Skipping {} ({}), already on Letterboxd",
+                movie.title,
+                movie.year
+            );
+            already_on_letterboxd.push(format!("{} ({})", movie.title, movie.year));
+            continue;
+        }
+        export(rating_transformer.apply(movie));
+    }
+    // The movies stop coming as well when fetching crashed, leaving them incomplete.
+    fetching
+        .await
+        .map_err(|e| format!("Error while fetching {}: {:?}", collection, e))??;
+    if !already_on_letterboxd.is_empty() {
+        log::info!(
+            "Left {} films out of the {} export, they are already on Letterboxd: {}",
+            already_on_letterboxd.len(),
+            collection,
+            already_on_letterboxd.join(", ")
+        );
+    }
+
+    Ok(already_on_letterboxd)
+}
+
+async fn fetch_and_export(
+    client: Arc<FilmowClient>,
+    user: Arc<String>,
+    collection: Collection,
+    progress: Arc<CollectionProgress>,
+    deduplicator: Arc<Deduplicator>,
+    exporters: Arc<Vec<Arc<dyn Exporter>>>,
+    config: Arc<RunConfig>,
+) -> Result<CollectionReport, String> {
+    let started = Instant::now();
+    let mut export_errors = vec![];
+    let mut sinks = vec![];
+    for exporter in exporters.iter() {
+        match exporter.open(collection, &config.output_dir) {
+            Ok(sink) => sinks.push((exporter.name(), sink, 0)),
+            Err(e) => {
+                log::error!(
+                    "Error when exporting {} with {}: {}",
+                    collection,
+                    exporter.name(),
+                    e
+                );
+                export_errors.push(format!("{}: {}", exporter.name(), e));
+            }
+        }
+    }
+
+    let rating_transformer = RatingTransformer::new(config.rating.clone());
+    let already_on_letterboxd = stream_exported_movies(
+        client,
+        user,
+        progress.clone(),
+        deduplicator,
+        &rating_transformer,
+        |movie| {
+            sinks.retain_mut(|(name, sink, written)| match sink.write(&movie) {
+                Ok(()) => {
+                    *written += 1;
+                    true
+                }
+                Err(e) => {
+                    log::error!("Error when exporting {} with {}: {}", collection, name, e);
+                    export_errors.push(format!("{}: {}", name, e));
+                    false
+                }
+            })
+        },
+    )
+    .await?;
+
+    let mut files = vec![];
+    let mut finished = 0;
+    // Sinks stop at their first failed write, so the films of the sink that wrote the most
+    // include those of every other one.
+    let mut exported = 0;
+    for (name, sink, written) in sinks {
+        match sink.finish(config.sort_output) {
+            Err(e) => {
+                log::error!("Error when exporting {} with {}: {}", collection, name, e);
+                export_errors.push(format!("{}: {}", name, e));
+            }
+            Ok(written_files) => {
+                for file in &written_files {
+                    log::info!(
+                        "Successfully generated {} file: {}",
+                        collection,
+                        file.display()
+                    );
+                    progress.export_written(name, file);
+                }
+                files.extend(written_files);
+                finished += 1;
+                exported = exported.max(written);
+            }
+        }
+    }
+
+    let failed_films = progress.failed_films();
+    let failed_pages = progress.failed_pages();
+    let failed_ratings = progress.failed_ratings();
+    let status = if progress.fetched_pages() == 0 || (!exporters.is_empty() && finished == 0) {
+        ExportStatus::Failed
+    } else if failed_films.is_empty()
+        && failed_pages.is_empty()
+        && failed_ratings.is_empty()
+        && export_errors.is_empty()
+    {
+        ExportStatus::Complete
+    } else {
+        ExportStatus::Partial
+    };
+    Ok(CollectionReport {
+        collection,
+        status,
+        found: progress.discovered_films(),
+        exported,
+        skipped: progress.skipped_films() + already_on_letterboxd.len(),
+        failed_films,
+        failed_pages,
+        failed_ratings,
+        export_errors,
+        files,
+        duration: started.elapsed(),
+    })
 }
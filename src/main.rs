@@ -1,9 +1,240 @@
-use std::{env, io, io::prelude::*};
+use std::{io, io::prelude::*, path::PathBuf, process::ExitCode, str::FromStr};
+
+use clap::{Args, Parser, Subcommand};
+use filmow2letterboxd::{ExportStatus, OverlapPolicy, RatingScale, RoundingMode, RunConfig};
 
 mod logging;
 
-fn get_username() -> String {
-    match env::args().nth(1) {
+/// Exit code of an export that wrote its files but left some films out of them.
+const PARTIAL_EXPORT: u8 = 2;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Migrates a Filmow profile to files Letterboxd can import",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks that Filmow pages can still be read, exiting non-zero when they can't.
+    Doctor(DoctorArgs),
+    /// Checks CSV files meant for the Letterboxd importer, exiting non-zero when any has errors.
+    Validate(ValidateArgs),
+    /// Compares exported files with a Letterboxd data export, exiting non-zero when films are
+    /// missing on Letterboxd.
+    Reconcile(ReconcileArgs),
+    /// Lists the films added, removed, re-rated or moved between two exports of a profile.
+    Diff(DiffArgs),
+    /// Fetches a profile without writing any file and prints an overview of its films.
+    Stats(StatsArgs),
+}
+
+#[derive(Args, Debug)]
+struct ExportArgs {
+    /// Filmow username. Prompted for when omitted.
+    user: Option<String>,
+
+    /// Exporters used to write every collection (letterboxd-csv, json).
+    #[arg(
+        long = "exporter",
+        value_delimiter = ',',
+        default_value = "letterboxd-csv"
+    )]
+    exporters: Vec<String>,
+
+    /// Directory where the exported files are written.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Scale of the exported ratings: five (0.5 to 5 stars) or ten (1 to 10 points).
+    #[arg(long, default_value = "five")]
+    rating_scale: RatingScale,
+
+    /// Rounding applied to ratings after scale conversion: none, half, nearest, down or up.
+    #[arg(long, default_value = "none")]
+    rating_rounding: RoundingMode,
+
+    /// Exports ratings below this value, in the target scale, as unrated.
+    #[arg(long)]
+    min_rating: Option<f32>,
+
+    /// Exports every movie as unrated.
+    #[arg(long)]
+    drop_ratings: bool,
+
+    /// What to do with films both watched and on the watchlist: keep them in both exports or
+    /// prefer-watched, dropping them from the watchlist.
+    #[arg(long, default_value = "keep")]
+    overlap: OverlapPolicy,
+
+    /// Splits CSV files into numbered parts of at most this many movies each.
+    #[arg(long, value_name = "ROWS")]
+    split_rows: Option<usize>,
+
+    /// Splits CSV files into numbered parts of at most this many bytes each.
+    #[arg(long, value_name = "BYTES")]
+    split_bytes: Option<u64>,
+
+    /// Leaves out films already in this Letterboxd data export: the downloaded ZIP, its
+    /// extracted folder or one of its CSV files.
+    #[arg(long, value_name = "PATH")]
+    skip_existing: Option<PathBuf>,
+
+    /// Leaves movies in the order they were fetched in instead of sorting the files by title.
+    #[arg(long)]
+    unsorted: bool,
+
+    /// Keeps a raw copy of every fetched Filmow page in this directory, with an index.json.
+    #[arg(long, value_name = "DIR", conflicts_with = "offline")]
+    archive: Option<PathBuf>,
+
+    /// Records every request made to Filmow, with its response, in this cassette file.
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Prints the built-in selectors, as a starting point for a selectors file, and exits.
+    #[arg(long)]
+    print_selectors: bool,
+
+    /// Hides the progress of the export, showing only the log.
+    #[arg(long)]
+    no_progress: bool,
+
+    #[command(flatten)]
+    source: SourceArgs,
+}
+
+/// Options controlling where Filmow pages are read from and how.
+#[derive(Args, Debug)]
+struct SourceArgs {
+    /// Reads Filmow pages saved in this directory instead of accessing the network.
+    #[arg(long, value_name = "DIR")]
+    offline: Option<PathBuf>,
+
+    /// Root of the Filmow site to read from, such as a local stand-in or a mirror.
+    #[arg(long, value_name = "URL", default_value = "https://filmow.com")]
+    base_url: String,
+
+    /// Serves the responses recorded in this cassette file instead of accessing Filmow.
+    #[arg(long, value_name = "FILE", conflicts_with = "offline")]
+    replay: Option<PathBuf>,
+
+    /// JSON file with selectors overriding the built-in ones used to read Filmow pages.
+    #[arg(long, value_name = "FILE")]
+    selectors: Option<PathBuf>,
+}
+
+impl SourceArgs {
+    fn into_config(self) -> RunConfig {
+        let mut config = RunConfig::default();
+        config.offline_dir = self.offline;
+        config.base_url = self.base_url;
+        config.replay_cassette = self.replay;
+        config.selectors_file = self.selectors;
+        config
+    }
+}
+
+#[derive(Args, Debug)]
+struct DoctorArgs {
+    /// Profile whose pages are checked. It should have watched films and a watchlist.
+    #[arg(long, default_value = "azael")]
+    profile: String,
+
+    /// Maximum number of film pages checked.
+    #[arg(long, default_value_t = 5)]
+    films: usize,
+
+    #[command(flatten)]
+    source: SourceArgs,
+}
+
+#[derive(Args, Debug)]
+struct ValidateArgs {
+    /// Files to check, such as the watched.csv and watchlist.csv generated by an export.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ReconcileArgs {
+    /// Letterboxd data export taken after importing: the downloaded ZIP, its extracted folder
+    /// or one of its CSV files.
+    letterboxd_export: PathBuf,
+
+    /// Directory with the files generated by the export.
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    exported: PathBuf,
+
+    /// Directory where the films missing on Letterboxd are written, as CSV files to import again.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Older export: the directory it was written to, or one of its CSV or JSON files.
+    old: PathBuf,
+
+    /// Newer export of the same profile, in the same form.
+    new: PathBuf,
+
+    /// Output format: text or json.
+    #[arg(long, default_value = "text")]
+    format: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct StatsArgs {
+    /// Filmow username. Prompted for when omitted.
+    user: Option<String>,
+
+    /// Output format: text, json or html, a standalone page.
+    #[arg(long, default_value = "text")]
+    format: OutputFormat,
+
+    /// Hides the progress line printed to stderr while fetching.
+    #[arg(long)]
+    no_progress: bool,
+
+    #[command(flatten)]
+    source: SourceArgs,
+}
+
+/// How reports of the commands are printed.
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Json,
+    Html,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!(
+                "Unknown output format '{}'. Expected 'text', 'json' or 'html'",
+                s
+            )),
+        }
+    }
+}
+
+fn get_username(cli_user: Option<String>) -> String {
+    match cli_user {
         None => {
             print!("Please, enter the your Filmow username: ");
             io::stdout().flush().expect("could not flush stdout");
@@ -11,15 +242,164 @@ fn get_username() -> String {
             io::stdin()
                 .read_line(&mut user_input)
                 .expect("Failed to read user input");
-            user_input
+            user_input.trim().to_string()
         }
         Some(user) => user,
     }
 }
 
+async fn export(args: ExportArgs) -> ExitCode {
+    if args.print_selectors {
+        println!("{}", filmow2letterboxd::default_selectors_json());
+        return ExitCode::SUCCESS;
+    }
+
+    let mut config = args.source.into_config();
+    config.exporters = args.exporters;
+    config.output_dir = args.output_dir;
+    config.rating.scale = args.rating_scale;
+    config.rating.rounding = args.rating_rounding;
+    config.rating.min_rating = args.min_rating;
+    config.rating.drop_ratings = args.drop_ratings;
+    config.archive_dir = args.archive;
+    config.record_cassette = args.record;
+    config.show_progress = !args.no_progress;
+    config.sort_output = !args.unsorted;
+    config.overlap_policy = args.overlap;
+    config.csv_chunks.max_rows = args.split_rows;
+    config.csv_chunks.max_bytes = args.split_bytes;
+    config.letterboxd_export = args.skip_existing;
+
+    match filmow2letterboxd::run_with_config(get_username(args.user), config).await {
+        Ok(report) => {
+            println!("{}", report);
+            match report.status() {
+                ExportStatus::Complete => ExitCode::SUCCESS,
+                ExportStatus::Partial => ExitCode::from(PARTIAL_EXPORT),
+                _ => ExitCode::FAILURE,
+            }
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn doctor(args: DoctorArgs) -> ExitCode {
+    match filmow2letterboxd::run_doctor(args.profile, args.films, args.source.into_config()).await {
+        Ok(report) => {
+            println!("{}", report);
+            if report.is_healthy() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn validate(args: ValidateArgs) -> ExitCode {
+    let mut valid = true;
+    for file in args.files {
+        match filmow2letterboxd::validate_import_file(&file) {
+            Ok(report) => {
+                println!("{}", report);
+                valid &= report.is_valid();
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                valid = false;
+            }
+        }
+    }
+
+    if valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn reconcile(args: ReconcileArgs) -> ExitCode {
+    match filmow2letterboxd::reconcile(&args.exported, &args.letterboxd_export, &args.output_dir) {
+        Ok(report) => {
+            println!("{}", report);
+            if report.is_complete() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn diff(args: DiffArgs) -> ExitCode {
+    if let OutputFormat::Html = args.format {
+        log::error!("Diffs can only be printed as text or json");
+        return ExitCode::FAILURE;
+    }
+
+    match filmow2letterboxd::diff_exports(&args.old, &args.new) {
+        Ok(diff) => {
+            match args.format {
+                OutputFormat::Text | OutputFormat::Html => println!("{}", diff),
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&diff).expect("Diffs are serializable")
+                ),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn stats(args: StatsArgs) -> ExitCode {
+    let mut config = args.source.into_config();
+    config.show_progress = !args.no_progress;
+
+    match filmow2letterboxd::run_stats(get_username(args.user), config).await {
+        Ok(stats) => {
+            match args.format {
+                OutputFormat::Text => println!("{}", stats),
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&stats).expect("Stats are serializable")
+                ),
+                OutputFormat::Html => print!("{}", stats.to_html()),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     logging::setup_logging();
 
-    filmow2letterboxd::run(get_username()).await;
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Doctor(args)) => doctor(args).await,
+        Some(Command::Validate(args)) => validate(args),
+        Some(Command::Reconcile(args)) => reconcile(args),
+        Some(Command::Diff(args)) => diff(args),
+        Some(Command::Stats(args)) => stats(args).await,
+        None => export(cli.export).await,
+    }
 }
@@ -0,0 +1,34 @@
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Collection {
+    Watched,
+    Watchlist,
+}
+
+impl Collection {
+    pub fn all() -> Vec<Collection> {
+        vec![Collection::Watched, Collection::Watchlist]
+    }
+
+    /// Base name used for the files generated for this collection, without extension.
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            Collection::Watched => "watched",
+            Collection::Watchlist => "watchlist",
+        }
+    }
+}
+
+impl fmt::Display for Collection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Collection::Watched => write!(f, "watched movies"),
+            Collection::Watchlist => write!(f, "watchlist"),
+        }
+    }
+}
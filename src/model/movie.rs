@@ -1,22 +1,14 @@
-#[derive(Debug, PartialOrd, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// A film as read from its Filmow page.
+#[derive(Debug, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Movie {
+    /// Original title of the film.
     pub title: String,
     pub director: Option<String>,
     pub year: u32,
+    /// Rating given by the user, from 0.5 to 5 stars on Filmow, in the scale of
+    /// `RatingOptions` once transformed.
     pub rating: Option<f32>,
 }
-
-impl Movie {
-    pub fn to_csvable_array(&self) -> Vec<String> {
-        return vec![
-            self.title.clone(),
-            self.director.clone().unwrap_or("".to_string()),
-            self.year.to_string(),
-            self.rating.map(|r| r.to_string()).unwrap_or("".to_string()),
-        ];
-    }
-
-    pub fn csv_titles() -> Vec<&'static str> {
-        return vec!["Title", "Directors", "Year", "Rating"];
-    }
-}
@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    config::RunConfig,
+    model::{collection::Collection, movie::Movie},
+    persisters::{json_exporter::JsonExporter, letterboxd_csv_exporter::LetterboxdCsvExporter},
+};
+
+/// An output format for the movies fetched from a Filmow collection.
+///
+/// Implement it to write movies somewhere else than the built-in exporters do, and add it to
+/// `RunConfig::custom_exporters`.
+pub trait Exporter: Send + Sync + Debug {
+    /// Name used to select this exporter from the configuration.
+    fn name(&self) -> &'static str;
+
+    /// Starts writing `collection` into `output_dir`, returning the sink its movies are
+    /// written to as they are fetched.
+    fn open(
+        &self,
+        collection: Collection,
+        output_dir: &Path,
+    ) -> Result<Box<dyn ExportSink>, String>;
+}
+
+/// Files of a single collection being written by an exporter.
+pub trait ExportSink: Send {
+    /// Appends `movie`, making it to disk before returning.
+    fn write(&mut self, movie: &Movie) -> Result<(), String>;
+
+    /// Completes the files, sorting their movies by title first when `sort` is set, and
+    /// returns their paths.
+    fn finish(self: Box<Self>, sort: bool) -> Result<Vec<PathBuf>, String>;
+}
+
+#[derive(Clone, Default)]
+pub struct ExporterRegistry {
+    exporters: BTreeMap<&'static str, Arc<dyn Exporter>>,
+}
+
+impl ExporterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_defaults(config: &RunConfig) -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(
+            LetterboxdCsvExporter::new(config.rating.scale).with_chunk_limits(config.csv_chunks),
+        ));
+        registry.register(Arc::new(JsonExporter::new()));
+        for exporter in &config.custom_exporters {
+            registry.register(exporter.clone());
+        }
+        registry
+    }
+
+    pub fn register(&mut self, exporter: Arc<dyn Exporter>) {
+        self.exporters.insert(exporter.name(), exporter);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.exporters.keys().copied().collect()
+    }
+
+    pub fn resolve(&self, names: &[String]) -> Result<Vec<Arc<dyn Exporter>>, String> {
+        names
+            .iter()
+            .map(|name| {
+                self.exporters.get(name.as_str()).cloned().ok_or_else(|| {
+                    format!(
+                        "Unknown exporter '{}'. Available exporters: {}",
+                        name,
+                        self.names().join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+}
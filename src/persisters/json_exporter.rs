@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    model::{collection::Collection, movie::Movie},
+    persisters::exporter::{ExportSink, Exporter},
+};
+
+/// Writes every movie of a collection as a pretty-printed JSON array.
+#[derive(Debug)]
+pub struct JsonExporter {}
+
+impl JsonExporter {
+    pub fn new() -> Self {
+        JsonExporter {}
+    }
+}
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn open(
+        &self,
+        collection: Collection,
+        output_dir: &Path,
+    ) -> Result<Box<dyn ExportSink>, String> {
+        let file_path = output_dir.join(format!("{}.json", collection.file_stem()));
+        let mut wrt = create_writer(&file_path)?;
+        write_flushed(&mut wrt, "[", &file_path)?;
+
+        Ok(Box::new(JsonSink {
+            file_path,
+            wrt,
+            written: 0,
+        }))
+    }
+}
+
+/// Writes the array one element at a time, laid out as `serde_json::to_writer_pretty` would.
+struct JsonSink {
+    file_path: PathBuf,
+    wrt: BufWriter<File>,
+    written: usize,
+}
+
+impl ExportSink for JsonSink {
+    fn write(&mut self, movie: &Movie) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(movie).map_err(|e| {
+            format!(
+                "Error when writing JSON file {}. {:?}",
+                self.file_path.display(),
+                e
+            )
+        })?;
+        let separator = if self.written == 0 { "\n" } else { ",\n" };
+        let element = json.replace('\n', "\n  ");
+        write_flushed(
+            &mut self.wrt,
+            &format!("{}  {}", separator, element),
+            &self.file_path,
+        )?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>, sort: bool) -> Result<Vec<PathBuf>, String> {
+        let end = if self.written == 0 { "]" } else { "\n]" };
+        write_flushed(&mut self.wrt, end, &self.file_path)?;
+        drop(self.wrt);
+
+        if sort {
+            sort_by_title(&self.file_path)?;
+        }
+
+        Ok(vec![self.file_path])
+    }
+}
+
+fn create_writer(file_path: &Path) -> Result<BufWriter<File>, String> {
+    let file = File::create(file_path).map_err(|e| {
+        format!(
+            "Could not create JSON file {}. {:?}",
+            file_path.display(),
+            e
+        )
+    })?;
+    Ok(BufWriter::new(file))
+}
+
+fn write_flushed(wrt: &mut BufWriter<File>, content: &str, file_path: &Path) -> Result<(), String> {
+    wrt.write_all(content.as_bytes())
+        .and_then(|_| wrt.flush())
+        .map_err(|e| {
+            format!(
+                "Error when writing JSON file {}. {:?}",
+                file_path.display(),
+                e
+            )
+        })
+}
+
+/// Rewrites the array at `file_path` with its movies sorted by title.
+fn sort_by_title(file_path: &Path) -> Result<(), String> {
+    let file_name = file_path.display();
+    let file = File::open(file_path)
+        .map_err(|e| format!("Could not read JSON file {}. {:?}", file_name, e))?;
+    let mut movies: Vec<Movie> = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Could not read JSON file {}. {:?}", file_name, e))?;
+    movies.sort_by(|a, b| a.title.cmp(&b.title));
+
+    let mut wrt = create_writer(file_path)?;
+    serde_json::to_writer_pretty(&mut wrt, &movies)
+        .map_err(|e| format!("Error when writing JSON file {}. {:?}", file_name, e))?;
+    wrt.flush()
+        .map_err(|e| format!("Error when flushing file {}. {:?}", file_name, e))
+}
@@ -0,0 +1,672 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use csv::{Reader, StringRecord, Writer};
+
+use crate::{
+    model::{collection::Collection, movie::Movie},
+    persisters::exporter::{ExportSink, Exporter},
+    transformers::rating_transformer::RatingScale,
+};
+
+/// Limits of every generated CSV file. When any is set, a collection going over them is split
+/// into numbered parts, each with its own header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChunkLimits {
+    /// Movies in each part, not counting the header.
+    pub max_rows: Option<usize>,
+    /// Size of each part in bytes, including the header. A movie is never split, so a part
+    /// holding a single movie may go over it.
+    pub max_bytes: Option<u64>,
+}
+
+impl ChunkLimits {
+    fn is_set(&self) -> bool {
+        self.max_rows.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// Lists the files written for a collection, so the next run only removes files it wrote.
+fn manifest_path(output_dir: &Path, collection: Collection) -> PathBuf {
+    output_dir.join(format!(".{}.csv-parts", collection.file_stem()))
+}
+
+/// Oldest year a film can be from, the one of the earliest surviving recordings.
+const FIRST_FILM_YEAR: u32 = 1874;
+/// How far in the future announced films can be dated.
+const ANNOUNCED_FILM_YEARS: u32 = 10;
+
+/// Columns of the CSV layout accepted by https://letterboxd.com/import/.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LetterboxdColumn {
+    Title,
+    Directors,
+    Year,
+    /// 0.5 to 5 stars, in steps of 0.5.
+    Rating,
+    /// 1 to 10 points.
+    Rating10,
+    /// Date in the YYYY-MM-DD format.
+    WatchedDate,
+    LetterboxdUri,
+    ImdbId,
+    TmdbId,
+    Tags,
+    Review,
+}
+
+impl LetterboxdColumn {
+    pub const ALL: [LetterboxdColumn; 11] = [
+        LetterboxdColumn::Title,
+        LetterboxdColumn::Directors,
+        LetterboxdColumn::Year,
+        LetterboxdColumn::Rating,
+        LetterboxdColumn::Rating10,
+        LetterboxdColumn::WatchedDate,
+        LetterboxdColumn::LetterboxdUri,
+        LetterboxdColumn::ImdbId,
+        LetterboxdColumn::TmdbId,
+        LetterboxdColumn::Tags,
+        LetterboxdColumn::Review,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LetterboxdColumn::Title => "Title",
+            LetterboxdColumn::Directors => "Directors",
+            LetterboxdColumn::Year => "Year",
+            LetterboxdColumn::Rating => "Rating",
+            LetterboxdColumn::Rating10 => "Rating10",
+            LetterboxdColumn::WatchedDate => "WatchedDate",
+            LetterboxdColumn::LetterboxdUri => "LetterboxdURI",
+            LetterboxdColumn::ImdbId => "imdbID",
+            LetterboxdColumn::TmdbId => "tmdbID",
+            LetterboxdColumn::Tags => "Tags",
+            LetterboxdColumn::Review => "Review",
+        }
+    }
+
+    /// Whether the importer can match films by this column, so rows need a value in one of them.
+    pub fn identifies_film(&self) -> bool {
+        matches!(
+            self,
+            LetterboxdColumn::Title
+                | LetterboxdColumn::LetterboxdUri
+                | LetterboxdColumn::ImdbId
+                | LetterboxdColumn::TmdbId
+        )
+    }
+
+    /// Column with the given header, which the importer matches ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|column| column.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Checks `value` is accepted in this column. Any column can be left empty.
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(());
+        }
+
+        match self {
+            LetterboxdColumn::Year => {
+                let last_year = current_year() + ANNOUNCED_FILM_YEARS;
+                match value.parse::<u32>() {
+                    Ok(year) if (FIRST_FILM_YEAR..=last_year).contains(&year) => Ok(()),
+                    Ok(year) => Err(format!(
+                        "Year {} is not between {} and {}",
+                        year, FIRST_FILM_YEAR, last_year
+                    )),
+                    Err(_) => Err(format!("Year '{}' is not a number", value)),
+                }
+            }
+            LetterboxdColumn::Rating => match value.parse::<f32>() {
+                Ok(rating) if (0.5..=5.0).contains(&rating) && (rating * 2.0).fract() == 0.0 => {
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "Rating '{}' is not between 0.5 and 5 in steps of 0.5",
+                    value
+                )),
+            },
+            LetterboxdColumn::Rating10 => match value.parse::<f32>() {
+                Ok(rating) if (1.0..=10.0).contains(&rating) && rating.fract() == 0.0 => Ok(()),
+                _ => Err(format!(
+                    "Rating10 '{}' is not a whole number from 1 to 10",
+                    value
+                )),
+            },
+            LetterboxdColumn::WatchedDate => check_date(value),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_date(value: &str) -> Result<(), String> {
+    let invalid = || Err(format!("WatchedDate '{}' is not a YYYY-MM-DD date", value));
+    let parts: Vec<_> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return invalid();
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return invalid();
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return invalid();
+    };
+
+    let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap_year => 29,
+        2 => 28,
+        _ => return invalid(),
+    };
+    if day == 0 || day > days_in_month {
+        return invalid();
+    }
+    Ok(())
+}
+
+fn current_year() -> u32 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    // Average length of a Gregorian year, close enough to tell the year.
+    1970 + (seconds / 31_556_952) as u32
+}
+
+/// Writes the CSV layout accepted by https://letterboxd.com/import/ and the watchlist importer.
+#[derive(Debug)]
+pub struct LetterboxdCsvExporter {
+    rating_scale: RatingScale,
+    chunk_limits: ChunkLimits,
+}
+
+impl LetterboxdCsvExporter {
+    pub fn new(rating_scale: RatingScale) -> Self {
+        LetterboxdCsvExporter {
+            rating_scale,
+            chunk_limits: ChunkLimits::default(),
+        }
+    }
+
+    pub fn with_chunk_limits(mut self, chunk_limits: ChunkLimits) -> Self {
+        self.chunk_limits = chunk_limits;
+        self
+    }
+
+    pub fn columns(&self) -> Vec<LetterboxdColumn> {
+        let rating_column = match self.rating_scale {
+            RatingScale::FiveStars => LetterboxdColumn::Rating,
+            RatingScale::TenPoints => LetterboxdColumn::Rating10,
+        };
+        vec![
+            LetterboxdColumn::Title,
+            LetterboxdColumn::Directors,
+            LetterboxdColumn::Year,
+            rating_column,
+        ]
+    }
+
+    fn to_record(movie: &Movie) -> Vec<String> {
+        vec![
+            movie.title.clone(),
+            movie.director.clone().unwrap_or_default(),
+            movie.year.to_string(),
+            movie.rating.map(|r| r.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+impl Exporter for LetterboxdCsvExporter {
+    fn name(&self) -> &'static str {
+        "letterboxd-csv"
+    }
+
+    fn open(
+        &self,
+        collection: Collection,
+        output_dir: &Path,
+    ) -> Result<Box<dyn ExportSink>, String> {
+        let header: StringRecord = self.columns().iter().map(|column| column.name()).collect();
+        let parts = PartWriter::create(output_dir, collection, header, self.chunk_limits, vec![])?;
+        Ok(Box::new(CsvSink { collection, parts }))
+    }
+}
+
+struct CsvSink {
+    collection: Collection,
+    parts: PartWriter,
+}
+
+impl ExportSink for CsvSink {
+    fn write(&mut self, movie: &Movie) -> Result<(), String> {
+        self.parts
+            .write(&StringRecord::from(LetterboxdCsvExporter::to_record(movie)))
+    }
+
+    fn finish(self: Box<Self>, sort: bool) -> Result<Vec<PathBuf>, String> {
+        let mut parts = self.parts.finish()?;
+        if sort {
+            parts = sort_by_title(parts)?;
+        }
+
+        let parts = parts.into_single_file_when_unsplit()?;
+        parts.remove_stale_files()?;
+        parts.save_manifest()?;
+        if parts.files.len() > 1 {
+            log::info!(
+                "Split {} into {} files: {}",
+                self.collection,
+                parts.files.len(),
+                parts
+                    .files
+                    .iter()
+                    .map(|part| format!("{} ({} movies)", part.path.display(), part.rows))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        Ok(parts.files.into_iter().map(|part| part.path).collect())
+    }
+}
+
+struct Part {
+    path: PathBuf,
+    rows: usize,
+}
+
+/// Files written for a collection, in order.
+struct Parts {
+    output_dir: PathBuf,
+    collection: Collection,
+    header: StringRecord,
+    limits: ChunkLimits,
+    files: Vec<Part>,
+    /// Every file created for the collection so far, including parts of an unsorted pass.
+    created: Vec<PathBuf>,
+}
+
+impl Parts {
+    /// Names the only part of a collection that was never split like an unsplit export.
+    fn into_single_file_when_unsplit(mut self) -> Result<Self, String> {
+        if self.limits.is_set() && self.files.len() == 1 {
+            let path = self
+                .output_dir
+                .join(format!("{}.csv", self.collection.file_stem()));
+            fs::rename(&self.files[0].path, &path).map_err(|e| {
+                format!(
+                    "Could not rename {} to {}. {:?}",
+                    self.files[0].path.display(),
+                    path.display(),
+                    e
+                )
+            })?;
+            self.files[0].path = path;
+        }
+        Ok(self)
+    }
+}
+
+impl Parts {
+    /// Deletes files written for the collection by an earlier pass or run that aren't part of
+    /// this export anymore, such as parts past the last one once sorting packed the records into
+    /// fewer parts. Files the exporter didn't write are left alone.
+    fn remove_stale_files(&self) -> Result<(), String> {
+        let manifest = manifest_path(&self.output_dir, self.collection);
+        let previous_run = match fs::read_to_string(&manifest) {
+            Ok(content) => content
+                .lines()
+                .filter(|name| !name.is_empty())
+                .map(|name| self.output_dir.join(name))
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        for path in self.created.iter().chain(&previous_run) {
+            if self.files.iter().any(|part| part.path == *path) || !path.is_file() {
+                continue;
+            }
+            log::debug!("Removing stale file {}", path.display());
+            fs::remove_file(path)
+                .map_err(|e| format!("Could not remove {}. {:?}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    fn save_manifest(&self) -> Result<(), String> {
+        let manifest = manifest_path(&self.output_dir, self.collection);
+        let names: String = self
+            .files
+            .iter()
+            .filter_map(|part| part.path.file_name())
+            .map(|name| format!("{}\n", name.to_string_lossy()))
+            .collect();
+        fs::write(&manifest, names)
+            .map_err(|e| format!("Could not write {}. {:?}", manifest.display(), e))
+    }
+}
+
+/// Writes records into as many parts as the limits require, flushing every record to disk.
+struct PartWriter {
+    parts: Parts,
+    current: Option<BufWriter<File>>,
+    bytes: u64,
+}
+
+impl PartWriter {
+    fn create(
+        output_dir: &Path,
+        collection: Collection,
+        header: StringRecord,
+        limits: ChunkLimits,
+        created: Vec<PathBuf>,
+    ) -> Result<Self, String> {
+        let mut writer = PartWriter {
+            parts: Parts {
+                output_dir: output_dir.to_path_buf(),
+                collection,
+                header,
+                limits,
+                files: vec![],
+                created,
+            },
+            current: None,
+            bytes: 0,
+        };
+        writer.start_part()?;
+        Ok(writer)
+    }
+
+    fn write(&mut self, record: &StringRecord) -> Result<(), String> {
+        let encoded = encode(record)?;
+        let part = self.parts.files.last().expect("A part is always started");
+        let limits = self.parts.limits;
+        let over_rows = limits.max_rows.is_some_and(|max| part.rows >= max);
+        let over_bytes = limits
+            .max_bytes
+            .is_some_and(|max| self.bytes + encoded.len() as u64 > max);
+        if part.rows > 0 && (over_rows || over_bytes) {
+            self.start_part()?;
+        }
+
+        self.write_encoded(&encoded)?;
+        self.parts
+            .files
+            .last_mut()
+            .expect("A part is always started")
+            .rows += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Parts, String> {
+        self.flush_current()?;
+        Ok(self.parts)
+    }
+
+    fn start_part(&mut self) -> Result<(), String> {
+        self.flush_current()?;
+
+        let stem = self.parts.collection.file_stem();
+        let path = if self.parts.limits.is_set() {
+            self.parts
+                .output_dir
+                .join(format!("{}-{}.csv", stem, self.parts.files.len() + 1))
+        } else {
+            self.parts.output_dir.join(format!("{}.csv", stem))
+        };
+        let file = File::create(&path).map_err(|e| {
+            format!(
+                "Could not create CSV Writer for file {}. {:?}",
+                path.display(),
+                e
+            )
+        })?;
+
+        self.current = Some(BufWriter::new(file));
+        self.bytes = 0;
+        if !self.parts.created.contains(&path) {
+            self.parts.created.push(path.clone());
+        }
+        self.parts.files.push(Part { path, rows: 0 });
+        let header = encode(&self.parts.header)?;
+        self.write_encoded(&header)
+    }
+
+    fn write_encoded(&mut self, encoded: &[u8]) -> Result<(), String> {
+        let path = &self
+            .parts
+            .files
+            .last()
+            .expect("A part is always started")
+            .path;
+        let wrt = self.current.as_mut().expect("A part is always started");
+        wrt.write_all(encoded)
+            .and_then(|_| wrt.flush())
+            .map_err(|e| {
+                format!(
+                    "Error when adding entry to Csv file {}. Error:{:?}",
+                    path.display(),
+                    e
+                )
+            })?;
+        self.bytes += encoded.len() as u64;
+        Ok(())
+    }
+
+    fn flush_current(&mut self) -> Result<(), String> {
+        if let Some(mut wrt) = self.current.take() {
+            wrt.flush().map_err(|e| {
+                format!(
+                    "Error when flushing file {}. {:?}",
+                    self.parts
+                        .files
+                        .last()
+                        .expect("A part is started")
+                        .path
+                        .display(),
+                    e
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn encode(record: &StringRecord) -> Result<Vec<u8>, String> {
+    let mut wrt = Writer::from_writer(vec![]);
+    wrt.write_record(record)
+        .map_err(|e| format!("Could not encode Csv record {:?}. {:?}", record, e))?;
+    wrt.into_inner()
+        .map_err(|e| format!("Could not encode Csv record {:?}. {:?}", record, e))
+}
+
+/// Rewrites every part with the records of all of them sorted by their first column, the title.
+fn sort_by_title(parts: Parts) -> Result<Parts, String> {
+    let mut records = vec![];
+    for part in &parts.files {
+        let file_name = part.path.display();
+        let mut rdr = Reader::from_path(&part.path)
+            .map_err(|e| format!("Could not read Csv file {}. {:?}", file_name, e))?;
+        for record in rdr.records() {
+            records.push(
+                record.map_err(|e| format!("Could not read Csv file {}. {:?}", file_name, e))?,
+            );
+        }
+    }
+    records.sort_by(|a, b| a.get(0).cmp(&b.get(0)));
+
+    let mut writer = PartWriter::create(
+        &parts.output_dir,
+        parts.collection,
+        parts.header,
+        parts.limits,
+        parts.created,
+    )?;
+    for record in &records {
+        writer.write(record)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movie(title: &str) -> Movie {
+        Movie {
+            title: title.to_string(),
+            director: None,
+            year: 2000,
+            rating: None,
+        }
+    }
+
+    /// Names of the files in `dir`, leaving out the manifest.
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn export(dir: &Path, limits: ChunkLimits, titles: &[String], sort: bool) -> Vec<PathBuf> {
+        let exporter = LetterboxdCsvExporter::new(RatingScale::FiveStars).with_chunk_limits(limits);
+        let mut sink = exporter.open(Collection::Watched, dir).unwrap();
+        for title in titles {
+            sink.write(&movie(title)).unwrap();
+        }
+        sink.finish(sort).unwrap()
+    }
+
+    #[test]
+    fn removes_parts_left_over_when_sorting_packs_fewer_parts() {
+        let dir = tempfile::tempdir().unwrap();
+        // Rows take 30 or 40 bytes next to the 28 bytes header, so 100 bytes fit a row of each
+        // but not two of 40 or two of 30 and one of 40.
+        let long = |c: char| format!("{}{}", c, c.to_string().repeat(31));
+        let short = |c: char| format!("{}{}", c, c.to_string().repeat(21));
+        let titles = [short('B'), short('D'), long('A'), long('C')];
+        let limits = ChunkLimits {
+            max_rows: None,
+            max_bytes: Some(100),
+        };
+
+        let unsorted_dir = tempfile::tempdir().unwrap();
+        assert_eq!(export(unsorted_dir.path(), limits, &titles, false).len(), 3);
+
+        let files = export(dir.path(), limits, &titles, true);
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            file_names(dir.path()),
+            vec!["watched-1.csv", "watched-2.csv"]
+        );
+        let content: String = files
+            .iter()
+            .map(|file| fs::read_to_string(file).unwrap())
+            .collect();
+        for title in &titles {
+            assert_eq!(content.matches(title.as_str()).count(), 1);
+        }
+    }
+
+    #[test]
+    fn removes_files_of_earlier_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("watched-leftovers.csv"), "Title\n").unwrap();
+        let titles = vec!["Alien".to_string(), "Dune".to_string()];
+        export(
+            dir.path(),
+            ChunkLimits {
+                max_rows: Some(1),
+                max_bytes: None,
+            },
+            &[titles.clone(), titles.clone()].concat(),
+            true,
+        );
+        assert_eq!(
+            file_names(dir.path()),
+            vec![
+                "watched-1.csv",
+                "watched-2.csv",
+                "watched-3.csv",
+                "watched-4.csv",
+                "watched-leftovers.csv"
+            ]
+        );
+
+        export(
+            dir.path(),
+            ChunkLimits {
+                max_rows: Some(1),
+                max_bytes: None,
+            },
+            &titles,
+            true,
+        );
+        assert_eq!(
+            file_names(dir.path()),
+            vec!["watched-1.csv", "watched-2.csv", "watched-leftovers.csv"]
+        );
+
+        export(
+            dir.path(),
+            ChunkLimits {
+                max_rows: Some(5),
+                max_bytes: None,
+            },
+            &titles,
+            true,
+        );
+        assert_eq!(
+            file_names(dir.path()),
+            vec!["watched-leftovers.csv", "watched.csv"]
+        );
+
+        export(dir.path(), ChunkLimits::default(), &titles, true);
+        assert_eq!(
+            file_names(dir.path()),
+            vec!["watched-leftovers.csv", "watched.csv"]
+        );
+    }
+
+    #[test]
+    fn keeps_files_it_did_not_write() {
+        let dir = tempfile::tempdir().unwrap();
+        for unrelated in ["watched-2.csv", "watched-7.csv"] {
+            fs::write(dir.path().join(unrelated), "Title\nUnrelated\n").unwrap();
+        }
+
+        export(
+            dir.path(),
+            ChunkLimits::default(),
+            &["Alien".to_string()],
+            true,
+        );
+
+        assert_eq!(
+            file_names(dir.path()),
+            vec!["watched-2.csv", "watched-7.csv", "watched.csv"]
+        );
+    }
+}
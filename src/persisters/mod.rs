@@ -1 +1,3 @@
-pub mod csv_writer;
+pub mod exporter;
+pub mod json_exporter;
+pub mod letterboxd_csv_exporter;
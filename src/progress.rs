@@ -0,0 +1,435 @@
+use std::{
+    fmt,
+    io::{self, IsTerminal, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    events::{ExportEvent, ExportObserver},
+    model::{collection::Collection, movie::Movie},
+    report::Failure,
+};
+
+/// How often the progress line is redrawn on a terminal.
+const TERMINAL_INTERVAL: Duration = Duration::from_millis(200);
+/// How often a progress line is printed when the output isn't a terminal, such as in CI logs or
+/// when piped to `tee`.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Counters of how far the fetching of a single collection has got, also passed on as events
+/// to the observers of the run.
+#[derive(Debug)]
+pub struct CollectionProgress {
+    collection: Collection,
+    started: Instant,
+    pages_discovered: AtomicUsize,
+    pages_fetched: AtomicUsize,
+    films_discovered: AtomicUsize,
+    films_resolved: AtomicUsize,
+    films_failed: AtomicUsize,
+    films_skipped: AtomicUsize,
+    failed_films: Mutex<Vec<Failure>>,
+    failed_pages: Mutex<Vec<Failure>>,
+    failed_ratings: Mutex<Vec<Failure>>,
+    observers: Vec<Arc<dyn ExportObserver>>,
+}
+
+impl CollectionProgress {
+    pub fn new(collection: Collection, observers: Vec<Arc<dyn ExportObserver>>) -> Self {
+        CollectionProgress {
+            collection,
+            started: Instant::now(),
+            pages_discovered: AtomicUsize::new(0),
+            pages_fetched: AtomicUsize::new(0),
+            films_discovered: AtomicUsize::new(0),
+            films_resolved: AtomicUsize::new(0),
+            films_failed: AtomicUsize::new(0),
+            films_skipped: AtomicUsize::new(0),
+            failed_films: Mutex::new(vec![]),
+            failed_pages: Mutex::new(vec![]),
+            failed_ratings: Mutex::new(vec![]),
+            observers,
+        }
+    }
+
+    pub fn pages_discovered(&self, pages: usize) {
+        let known = self.pages_discovered.fetch_max(pages, Ordering::Relaxed);
+        for page in known + 1..=pages {
+            self.emit(ExportEvent::PageDiscovered {
+                collection: self.collection,
+                page,
+            });
+        }
+    }
+
+    pub fn page_fetched(&self, page: usize) {
+        self.pages_fetched.fetch_add(1, Ordering::Relaxed);
+        self.emit(ExportEvent::PageFetched {
+            collection: self.collection,
+            page,
+        });
+    }
+
+    pub fn films_discovered(&self, films: usize) {
+        self.films_discovered.fetch_add(films, Ordering::Relaxed);
+    }
+
+    pub fn film_resolved(&self, url: &str, movie: &Movie) {
+        self.films_resolved.fetch_add(1, Ordering::Relaxed);
+        self.emit(ExportEvent::FilmResolved {
+            collection: self.collection,
+            url: url.to_string(),
+            movie: movie.clone(),
+        });
+    }
+
+    pub fn film_failed(&self, url: &str, reason: &str) {
+        self.films_failed.fetch_add(1, Ordering::Relaxed);
+        push_failure(&self.failed_films, url, reason);
+        self.emit(ExportEvent::FilmFailed {
+            collection: self.collection,
+            url: url.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Records a listing page that couldn't be fetched, whose films are missing from the export.
+    pub fn page_failed(&self, url: &str, reason: &str) {
+        push_failure(&self.failed_pages, url, reason);
+        self.emit(ExportEvent::PageFailed {
+            collection: self.collection,
+            url: url.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Records a film exported unrated because its rating couldn't be understood.
+    pub fn rating_failed(&self, url: &str, reason: &str) {
+        push_failure(&self.failed_ratings, url, reason);
+        self.emit(ExportEvent::RatingFailed {
+            collection: self.collection,
+            url: url.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn export_written(&self, exporter: &str, file: &Path) {
+        self.emit(ExportEvent::ExportWritten {
+            collection: self.collection,
+            exporter: exporter.to_string(),
+            file: file.to_path_buf(),
+        });
+    }
+
+    /// Counts a film left out of the export, such as a duplicate or a watched film on the
+    /// watchlist.
+    pub fn film_skipped(&self) {
+        self.films_skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn collection(&self) -> Collection {
+        self.collection
+    }
+
+    pub fn fetched_pages(&self) -> usize {
+        self.pages_fetched.load(Ordering::Relaxed)
+    }
+
+    pub fn discovered_films(&self) -> usize {
+        self.films_discovered.load(Ordering::Relaxed)
+    }
+
+    pub fn skipped_films(&self) -> usize {
+        self.films_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Films whose pages couldn't be read so far.
+    pub fn failed_films(&self) -> Vec<Failure> {
+        self.failed_films
+            .lock()
+            .expect("Progress lock poisoned")
+            .clone()
+    }
+
+    pub fn failed_pages(&self) -> Vec<Failure> {
+        self.failed_pages
+            .lock()
+            .expect("Progress lock poisoned")
+            .clone()
+    }
+
+    pub fn failed_ratings(&self) -> Vec<Failure> {
+        self.failed_ratings
+            .lock()
+            .expect("Progress lock poisoned")
+            .clone()
+    }
+
+    fn emit(&self, event: ExportEvent) {
+        for observer in &self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            collection: self.collection,
+            elapsed: self.started.elapsed(),
+            pages_discovered: self.pages_discovered.load(Ordering::Relaxed),
+            pages_fetched: self.pages_fetched.load(Ordering::Relaxed),
+            films_discovered: self.films_discovered.load(Ordering::Relaxed),
+            films_resolved: self.films_resolved.load(Ordering::Relaxed),
+            films_failed: self.films_failed.load(Ordering::Relaxed),
+            films_skipped: self.films_skipped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct ProgressSnapshot {
+    collection: Collection,
+    elapsed: Duration,
+    pages_discovered: usize,
+    pages_fetched: usize,
+    films_discovered: usize,
+    films_resolved: usize,
+    films_failed: usize,
+    films_skipped: usize,
+}
+
+impl ProgressSnapshot {
+    fn films_done(&self) -> usize {
+        self.films_resolved + self.films_failed + self.films_skipped
+    }
+
+    fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.films_done() as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Time left for the films found so far, unknown until some of them were fetched.
+    fn eta(&self) -> Option<Duration> {
+        let throughput = self.throughput();
+        if throughput <= 0.0 {
+            return None;
+        }
+        let remaining = self.films_discovered.saturating_sub(self.films_done());
+        Some(Duration::from_secs_f64(remaining as f64 / throughput))
+    }
+}
+
+impl fmt::Display for ProgressSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: pages {}/{}, films {}/{} ({} failed, {} skipped), {:.1} films/s",
+            self.collection,
+            self.pages_fetched,
+            self.pages_discovered,
+            self.films_done(),
+            self.films_discovered,
+            self.films_failed,
+            self.films_skipped,
+            self.throughput()
+        )?;
+        match self.eta() {
+            Some(eta) => write!(f, ", ETA {}", format_duration(eta)),
+            None => write!(f, ", ETA unknown"),
+        }
+    }
+}
+
+fn push_failure(failures: &Mutex<Vec<Failure>>, url: &str, reason: &str) {
+    failures
+        .lock()
+        .expect("Progress lock poisoned")
+        .push(Failure {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        });
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Periodically prints the progress of every collection to stderr, redrawing a single line
+/// on a terminal and printing plain lines otherwise. Stdout is checked as well, as the line
+/// redrawing would garble the output of `filmow2letterboxd user | tee log`.
+pub struct ProgressReporter {
+    stop: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+impl ProgressReporter {
+    pub fn start(collections: Vec<Arc<CollectionProgress>>) -> Self {
+        let terminal = io::stdout().is_terminal() && io::stderr().is_terminal();
+        let interval = if terminal {
+            TERMINAL_INTERVAL
+        } else {
+            PLAIN_INTERVAL
+        };
+        let (stop, mut stopped) = watch::channel(false);
+
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately, when nothing was fetched yet.
+            ticker.tick().await;
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => print_progress(&collections, terminal, false),
+                    _ = stopped.changed() => break,
+                }
+            }
+            print_progress(&collections, terminal, true);
+        });
+
+        ProgressReporter { stop, handle }
+    }
+
+    /// Prints the final progress and stops reporting.
+    pub async fn finish(self) {
+        let _ = self.stop.send(true);
+        if let Err(e) = self.handle.await {
+            log::error!("Progress reporting failed: {}", e);
+        }
+    }
+}
+
+fn print_progress(collections: &[Arc<CollectionProgress>], terminal: bool, last: bool) {
+    let mut stderr = io::stderr().lock();
+    let _ = write_progress(&mut stderr, collections, terminal, last);
+    let _ = stderr.flush();
+}
+
+fn write_progress(
+    out: &mut impl Write,
+    collections: &[Arc<CollectionProgress>],
+    terminal: bool,
+    last: bool,
+) -> io::Result<()> {
+    let line = collections
+        .iter()
+        .map(|progress| progress.snapshot().to_string())
+        .collect::<Vec<_>>()
+        .join(" | ");
+
+    if terminal {
+        // Clears what is left of a longer previous line before redrawing.
+        write!(out, "\r\x1b[2K{}{}", line, if last { "\n" } else { "" })
+    } else {
+        writeln!(out, "Progress: {}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(
+        elapsed: Duration,
+        resolved: usize,
+        failed: usize,
+        skipped: usize,
+    ) -> ProgressSnapshot {
+        ProgressSnapshot {
+            collection: Collection::Watched,
+            elapsed,
+            pages_discovered: 3,
+            pages_fetched: 2,
+            films_discovered: 40,
+            films_resolved: resolved,
+            films_failed: failed,
+            films_skipped: skipped,
+        }
+    }
+
+    #[test]
+    fn describes_progress_with_throughput_and_eta() {
+        let snapshot = snapshot(Duration::from_secs(10), 17, 2, 1);
+
+        assert_eq!(snapshot.films_done(), 20);
+        assert_eq!(snapshot.throughput(), 2.0);
+        assert_eq!(snapshot.eta(), Some(Duration::from_secs(10)));
+        assert_eq!(
+            snapshot.to_string(),
+            "watched movies: pages 2/3, films 20/40 (2 failed, 1 skipped), 2.0 films/s, ETA 0:10"
+        );
+    }
+
+    #[test]
+    fn leaves_eta_unknown_until_films_are_done() {
+        let snapshot = snapshot(Duration::ZERO, 0, 0, 0);
+
+        assert_eq!(snapshot.throughput(), 0.0);
+        assert_eq!(snapshot.eta(), None);
+        assert!(snapshot.to_string().ends_with("0.0 films/s, ETA unknown"));
+    }
+
+    #[test]
+    fn formats_long_etas_in_minutes() {
+        let snapshot = snapshot(Duration::from_secs(300), 4, 0, 0);
+
+        assert_eq!(snapshot.eta(), Some(Duration::from_secs(2700)));
+        assert!(snapshot.to_string().ends_with("ETA 45:00"));
+    }
+
+    #[test]
+    fn counts_events_of_a_collection() {
+        let progress = CollectionProgress::new(Collection::Watchlist, vec![]);
+        progress.pages_discovered(2);
+        progress.page_fetched(1);
+        progress.films_discovered(3);
+        progress.film_skipped();
+        progress.film_failed("https://filmow.com/filme-t1/", "Not found");
+
+        let snapshot = progress.snapshot();
+        assert_eq!((snapshot.pages_discovered, snapshot.pages_fetched), (2, 1));
+        assert_eq!(snapshot.films_done(), 2);
+        assert_eq!(
+            progress.failed_films(),
+            vec![Failure {
+                url: "https://filmow.com/filme-t1/".to_string(),
+                reason: "Not found".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn prints_plain_lines_when_not_on_a_terminal() {
+        let collections: Vec<_> = [Collection::Watched, Collection::Watchlist]
+            .into_iter()
+            .map(|collection| Arc::new(CollectionProgress::new(collection, vec![])))
+            .collect();
+        collections[0].films_discovered(2);
+
+        let mut plain = vec![];
+        write_progress(&mut plain, &collections, false, false).unwrap();
+        write_progress(&mut plain, &collections, false, true).unwrap();
+        let plain = String::from_utf8(plain).unwrap();
+
+        assert_eq!(plain.lines().count(), 2);
+        assert!(plain
+            .lines()
+            .all(|line| line.starts_with("Progress: watched movies: ")
+                && line.contains(" | watchlist: ")));
+        assert!(!plain.contains('\r') && !plain.contains('\x1b'));
+
+        let mut redrawn = vec![];
+        write_progress(&mut redrawn, &collections, true, false).unwrap();
+        assert!(String::from_utf8(redrawn)
+            .unwrap()
+            .starts_with("\r\x1b[2Kwatched movies: "));
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, StringRecord, Writer};
+
+use crate::{
+    exported_files::{collection_files, film_from_record, ExportedFilm},
+    letterboxd_export::LetterboxdExport,
+    model::collection::Collection,
+    persisters::letterboxd_csv_exporter::LetterboxdColumn,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RatingMismatch {
+    pub film: ExportedFilm,
+    pub letterboxd_rating: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct YearMismatch {
+    pub film: ExportedFilm,
+    pub letterboxd_year: u32,
+}
+
+/// How the exported files of a collection compare to its Letterboxd counterpart.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CollectionReconciliation {
+    pub collection: Collection,
+    pub files: Vec<PathBuf>,
+    pub exported: usize,
+    /// Exported films Letterboxd has no film with the same title and a close year for.
+    pub missing: Vec<ExportedFilm>,
+    pub rating_mismatches: Vec<RatingMismatch>,
+    /// Exported films only found on Letterboxd under a year up to `YEAR_TOLERANCE` apart.
+    pub year_mismatches: Vec<YearMismatch>,
+    /// CSV file with the rows of the missing films, ready to be imported again.
+    pub leftovers: Option<PathBuf>,
+}
+
+/// Comparison of the files generated by an export with a Letterboxd data export.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ReconciliationReport {
+    pub collections: Vec<CollectionReconciliation>,
+}
+
+impl ReconciliationReport {
+    /// Whether every exported film was found on Letterboxd.
+    pub fn is_complete(&self) -> bool {
+        self.collections
+            .iter()
+            .all(|collection| collection.missing.is_empty())
+    }
+}
+
+impl fmt::Display for ReconciliationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, result) in self.collections.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{}: {} of {} exported films found on Letterboxd ({})",
+                result.collection,
+                result.exported - result.missing.len(),
+                result.exported,
+                result
+                    .files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            for film in &result.missing {
+                write!(f, "\n  Missing: {}", film)?;
+            }
+            for mismatch in &result.rating_mismatches {
+                write!(
+                    f,
+                    "\n  Rating mismatch: {} rated {}",
+                    mismatch.film,
+                    mismatch.film.rating.unwrap_or_default()
+                )?;
+                match mismatch.letterboxd_rating {
+                    Some(rating) => write!(f, ", {} on Letterboxd", rating)?,
+                    None => write!(f, ", not rated on Letterboxd")?,
+                }
+            }
+            for mismatch in &result.year_mismatches {
+                write!(
+                    f,
+                    "\n  Year mismatch: {} listed as {} on Letterboxd",
+                    mismatch.film, mismatch.letterboxd_year
+                )?;
+            }
+            if let Some(leftovers) = &result.leftovers {
+                write!(
+                    f,
+                    "\n  Wrote the {} missing films to {}, ready to import again",
+                    result.missing.len(),
+                    leftovers.display()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares the files an export generated in `exported_dir` with the Letterboxd data export at
+/// `letterboxd_export`, writing the rows of films missing on Letterboxd to `output_dir`.
+pub fn reconcile(
+    exported_dir: &Path,
+    letterboxd_export: &Path,
+    output_dir: &Path,
+) -> Result<ReconciliationReport, String> {
+    let letterboxd = LetterboxdExport::load(letterboxd_export)?;
+
+    let mut collections = vec![];
+    for collection in Collection::all() {
+        let files = collection_files(exported_dir, collection, "csv")?;
+        if !files.is_empty() {
+            collections.push(reconcile_collection(
+                collection,
+                files,
+                &letterboxd,
+                output_dir,
+            )?);
+        }
+    }
+
+    if collections.is_empty() {
+        return Err(format!(
+            "No exported files found in {}",
+            exported_dir.display()
+        ));
+    }
+    Ok(ReconciliationReport { collections })
+}
+
+fn reconcile_collection(
+    collection: Collection,
+    files: Vec<PathBuf>,
+    letterboxd: &LetterboxdExport,
+    output_dir: &Path,
+) -> Result<CollectionReconciliation, String> {
+    let mut result = CollectionReconciliation {
+        collection,
+        files: vec![],
+        exported: 0,
+        missing: vec![],
+        rating_mismatches: vec![],
+        year_mismatches: vec![],
+        leftovers: None,
+    };
+    let mut header: Option<StringRecord> = None;
+    let mut leftover_rows = vec![];
+
+    for file in files {
+        let mut rdr = ReaderBuilder::new()
+            .from_path(&file)
+            .map_err(|e| format!("Failed to read {}. Error: {}", file.display(), e))?;
+        let file_header = rdr
+            .headers()
+            .map_err(|e| format!("Invalid CSV file {}. Error: {}", file.display(), e))?
+            .clone();
+        match &header {
+            Some(header) if *header != file_header => {
+                return Err(format!(
+                    "{} has different columns than {}",
+                    file.display(),
+                    result.files[0].display()
+                ))
+            }
+            Some(_) => {}
+            None => header = Some(file_header.clone()),
+        }
+        let columns: Vec<_> = file_header
+            .iter()
+            .map(LetterboxdColumn::from_name)
+            .collect();
+
+        for record in rdr.records() {
+            let record =
+                record.map_err(|e| format!("Invalid CSV file {}. Error: {}", file.display(), e))?;
+            let Some(film) = film_from_record(&record, &columns) else {
+                log::warn!(
+                    "Ignoring row of {} without a title and year: {:?}",
+                    file.display(),
+                    record
+                );
+                continue;
+            };
+            result.exported += 1;
+
+            let found = letterboxd.find(collection, &film.title, film.year);
+            if let Some(found) = found.filter(|found| found.year == film.year) {
+                let rating_differs = match found.rating {
+                    Some(rating) => film
+                        .rating
+                        .is_some_and(|exported| (exported - rating).abs() > f32::EPSILON),
+                    None => film.rating.is_some(),
+                };
+                if collection == Collection::Watched && rating_differs {
+                    result.rating_mismatches.push(RatingMismatch {
+                        letterboxd_rating: found.rating,
+                        film,
+                    });
+                }
+            } else if let Some(found) = found {
+                result.year_mismatches.push(YearMismatch {
+                    letterboxd_year: found.year,
+                    film,
+                });
+            } else {
+                result.missing.push(film);
+                leftover_rows.push(record);
+            }
+        }
+        result.files.push(file);
+    }
+
+    if let (Some(header), false) = (header, leftover_rows.is_empty()) {
+        let leftovers = output_dir.join(format!("{}-leftovers.csv", collection.file_stem()));
+        write_rows(&leftovers, &header, &leftover_rows)?;
+        result.leftovers = Some(leftovers);
+    }
+
+    Ok(result)
+}
+
+fn write_rows(path: &Path, header: &StringRecord, rows: &[StringRecord]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create directory {}. Error: {}", dir.display(), e))?;
+    }
+    let mut wtr = Writer::from_path(path)
+        .map_err(|e| format!("Failed to create file {}. Error: {}", path.display(), e))?;
+    for row in std::iter::once(header).chain(rows) {
+        wtr.write_record(row)
+            .map_err(|e| format!("Failed to write to {}. Error: {}", path.display(), e))?;
+    }
+    wtr.flush()
+        .map_err(|e| format!("Failed to write to {}. Error: {}", path.display(), e))
+}
@@ -0,0 +1,129 @@
+use std::{fmt, path::PathBuf, time::Duration};
+
+use crate::{model::collection::Collection, transformers::deduplicator::DeduplicationReport};
+
+/// How far a run, or a single collection of it, got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ExportStatus {
+    /// Every film listed on the profile was exported.
+    Complete,
+    /// Files were written, but some films, ratings or listing pages are missing from them.
+    Partial,
+    /// Nothing usable was written, such as when the listing pages couldn't be read at all.
+    Failed,
+}
+
+/// A page that couldn't be fetched or read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Failure {
+    pub url: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CollectionReport {
+    pub collection: Collection,
+    pub status: ExportStatus,
+    /// Films listed on the profile.
+    pub found: usize,
+    /// Films written by the exporters.
+    pub exported: usize,
+    /// Films left out on purpose, such as duplicates or films already on Letterboxd.
+    pub skipped: usize,
+    pub failed_films: Vec<Failure>,
+    /// Listing pages that couldn't be fetched, whose films are missing from `found`.
+    pub failed_pages: Vec<Failure>,
+    /// Films exported unrated because their rating couldn't be understood.
+    pub failed_ratings: Vec<Failure>,
+    /// Errors of exporters that couldn't write this collection.
+    pub export_errors: Vec<String>,
+    pub files: Vec<PathBuf>,
+    pub duration: Duration,
+}
+
+/// Outcome of a run, returned once every collection was exported.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ExportReport {
+    pub collections: Vec<CollectionReport>,
+    pub deduplication: DeduplicationReport,
+    /// Errors once the collections were exported, such as failing to save the archive index.
+    pub errors: Vec<String>,
+    pub duration: Duration,
+}
+
+impl ExportReport {
+    /// The worst status of any collection, and partial when a later step failed.
+    pub fn status(&self) -> ExportStatus {
+        let status = self
+            .collections
+            .iter()
+            .map(|collection| collection.status)
+            .max()
+            .unwrap_or(ExportStatus::Complete);
+        if self.errors.is_empty() {
+            status
+        } else {
+            status.max(ExportStatus::Partial)
+        }
+    }
+}
+
+impl fmt::Display for ExportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for report in &self.collections {
+            writeln!(
+                f,
+                "{}: {} of {} films exported, {} skipped, {} failed in {:.1}s",
+                report.collection,
+                report.exported,
+                report.found,
+                report.skipped,
+                report.failed_films.len(),
+                report.duration.as_secs_f64()
+            )?;
+            for failure in &report.failed_films {
+                writeln!(f, "  Failed film {}: {}", failure.url, failure.reason)?;
+            }
+            for failure in &report.failed_pages {
+                writeln!(f, "  Failed page {}: {}", failure.url, failure.reason)?;
+            }
+            for failure in &report.failed_ratings {
+                writeln!(f, "  Dropped rating of {}: {}", failure.url, failure.reason)?;
+            }
+            for error in &report.export_errors {
+                writeln!(f, "  Export error: {}", error)?;
+            }
+            if report.files.len() > 1 {
+                let files: Vec<_> = report
+                    .files
+                    .iter()
+                    .map(|file| file.display().to_string())
+                    .collect();
+                writeln!(
+                    f,
+                    "  Written to {} files: {}",
+                    files.len(),
+                    files.join(", ")
+                )?;
+            }
+        }
+        for error in &self.errors {
+            writeln!(f, "Error: {}", error)?;
+        }
+        let status = match self.status() {
+            ExportStatus::Complete => "complete",
+            ExportStatus::Partial => "partial",
+            ExportStatus::Failed => "failed",
+        };
+        write!(
+            f,
+            "Export {} in {:.1}s",
+            status,
+            self.duration.as_secs_f64()
+        )
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use serde::Serialize;
+
+use crate::{builder::FetchedCollection, model::collection::Collection};
+
+/// Number of directors listed by `ProfileStats::top_directors`.
+const TOP_DIRECTORS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct CollectionStats {
+    pub collection: Collection,
+    pub films: usize,
+    /// Films listed on the profile whose pages couldn't be read.
+    pub failed: usize,
+    /// Listing pages that couldn't be fetched.
+    pub failed_pages: usize,
+    /// Share of the listed films that failed, from 0 to 1. Films of failed listing pages are
+    /// estimated from the films of the pages that were fetched.
+    pub failure_rate: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct DirectorCount {
+    pub director: String,
+    pub films: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct RatingCount {
+    /// Rating from 0.5 to 5 stars, ratings in between rounded to the nearest half star.
+    pub rating: f32,
+    pub films: usize,
+}
+
+/// Overview of a profile. Decades, directors and ratings cover watched films only.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct ProfileStats {
+    pub user: String,
+    pub collections: Vec<CollectionStats>,
+    /// Watched films per release decade, keyed by its first year.
+    pub decades: BTreeMap<u32, usize>,
+    pub top_directors: Vec<DirectorCount>,
+    pub rating_histogram: Vec<RatingCount>,
+    pub average_rating: Option<f32>,
+    pub unrated: usize,
+}
+
+impl ProfileStats {
+    /// Computes the stats of the movies fetched for every collection.
+    pub fn compute(user: &str, fetched: &[FetchedCollection]) -> Self {
+        let collections = fetched
+            .iter()
+            .map(|fetched| CollectionStats {
+                collection: fetched.collection,
+                films: fetched.movies.len(),
+                failed: fetched.failed,
+                failed_pages: fetched.failed_pages,
+                failure_rate: failure_rate(fetched),
+            })
+            .collect();
+        let watched: Vec<_> = fetched
+            .iter()
+            .filter(|fetched| fetched.collection == Collection::Watched)
+            .flat_map(|fetched| &fetched.movies)
+            .collect();
+
+        let mut decades = BTreeMap::new();
+        let mut directors: HashMap<&str, usize> = HashMap::new();
+        let mut histogram = [0; 10];
+        let mut ratings = vec![];
+        for movie in &watched {
+            *decades.entry(movie.year / 10 * 10).or_default() += 1;
+            for director in movie.director.iter().flat_map(|d| d.split(',')) {
+                let director = director.trim();
+                if !director.is_empty() {
+                    *directors.entry(director).or_default() += 1;
+                }
+            }
+            if let Some(rating) = movie.rating {
+                let half_stars = ((rating * 2.0).round() as usize).clamp(1, histogram.len());
+                histogram[half_stars - 1] += 1;
+                ratings.push(rating);
+            }
+        }
+
+        let mut top_directors: Vec<_> = directors
+            .into_iter()
+            .map(|(director, films)| DirectorCount {
+                director: director.to_string(),
+                films,
+            })
+            .collect();
+        top_directors.sort_by(|a, b| b.films.cmp(&a.films).then(a.director.cmp(&b.director)));
+        top_directors.truncate(TOP_DIRECTORS);
+
+        ProfileStats {
+            user: user.to_string(),
+            collections,
+            decades,
+            top_directors,
+            rating_histogram: histogram
+                .iter()
+                .enumerate()
+                .map(|(index, films)| RatingCount {
+                    rating: (index + 1) as f32 / 2.0,
+                    films: *films,
+                })
+                .collect(),
+            average_rating: (!ratings.is_empty())
+                .then(|| ratings.iter().sum::<f32>() / ratings.len() as f32),
+            unrated: watched.len() - ratings.len(),
+        }
+    }
+
+    /// Standalone HTML page with the stats, without external styles or scripts.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!(
+            "<title>Filmow stats of {}</title>\n",
+            escape_html(&self.user)
+        ));
+        html.push_str(
+            "<style>\n\
+             body { font-family: sans-serif; max-width: 48rem; margin: 2rem auto; color: #222; }\n\
+             table { border-collapse: collapse; margin-bottom: 1.5rem; }\n\
+             th, td { text-align: left; padding: 0.2rem 0.8rem 0.2rem 0; }\n\
+             .bar { background: #00c030; height: 0.9rem; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+        html.push_str(&format!(
+            "<h1>Filmow stats of {}</h1>\n",
+            escape_html(&self.user)
+        ));
+
+        html.push_str(
+            "<h2>Collections</h2>\n<table>\n\
+             <tr><th>Collection</th><th>Films</th><th>Failed</th><th>Failed pages</th>\
+             <th>Failure rate</th></tr>\n",
+        );
+        for stats in &self.collections {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}%</td></tr>\n",
+                stats.collection,
+                stats.films,
+                stats.failed,
+                stats.failed_pages,
+                stats.failure_rate * 100.0
+            ));
+        }
+        html.push_str("</table>\n");
+
+        let decades: Vec<_> = self
+            .decades
+            .iter()
+            .map(|(decade, films)| (format!("{}s", decade), *films))
+            .collect();
+        push_bar_table(&mut html, "Films per decade", "Decade", &decades);
+
+        html.push_str(
+            "<h2>Top directors</h2>\n<table>\n<tr><th>Director</th><th>Films</th></tr>\n",
+        );
+        for director in &self.top_directors {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&director.director),
+                director.films
+            ));
+        }
+        html.push_str("</table>\n");
+
+        let ratings: Vec<_> = self
+            .rating_histogram
+            .iter()
+            .map(|count| (count.rating.to_string(), count.films))
+            .collect();
+        push_bar_table(&mut html, "Ratings", "Rating", &ratings);
+        html.push_str(&format!(
+            "<p>Average rating: {}. Unrated films: {}.</p>\n",
+            average_text(self.average_rating),
+            self.unrated
+        ));
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl fmt::Display for ProfileStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Filmow stats of {}", self.user)?;
+        for stats in &self.collections {
+            writeln!(
+                f,
+                "{}: {} films, {} failed ({:.1}%), failed listing pages: {}",
+                stats.collection,
+                stats.films,
+                stats.failed,
+                stats.failure_rate * 100.0,
+                stats.failed_pages
+            )?;
+        }
+
+        writeln!(f, "\nFilms per decade:")?;
+        for (decade, films) in &self.decades {
+            writeln!(f, "  {}s: {}", decade, films)?;
+        }
+
+        writeln!(f, "\nTop directors:")?;
+        for director in &self.top_directors {
+            writeln!(f, "  {}: {}", director.director, director.films)?;
+        }
+
+        writeln!(f, "\nRatings:")?;
+        let most_rated = self
+            .rating_histogram
+            .iter()
+            .map(|count| count.films)
+            .max()
+            .unwrap_or_default();
+        for count in &self.rating_histogram {
+            let bar_width = (count.films * 20)
+                .checked_div(most_rated)
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "  {:>3}: {:<20} {}",
+                count.rating,
+                "#".repeat(bar_width),
+                count.films
+            )?;
+        }
+        write!(
+            f,
+            "Average rating: {}, unrated films: {}",
+            average_text(self.average_rating),
+            self.unrated
+        )
+    }
+}
+
+fn failure_rate(fetched: &FetchedCollection) -> f64 {
+    let read = (fetched.movies.len() + fetched.failed) as f64;
+    let missing = match fetched.fetched_pages {
+        _ if fetched.failed_pages == 0 => 0.0,
+        0 => return 1.0,
+        pages => fetched.failed_pages as f64 * read / pages as f64,
+    };
+    let listed = read + missing;
+    if listed > 0.0 {
+        (fetched.failed as f64 + missing) / listed
+    } else {
+        0.0
+    }
+}
+
+fn push_bar_table(html: &mut String, title: &str, label: &str, rows: &[(String, usize)]) {
+    let most = rows
+        .iter()
+        .map(|(_, films)| *films)
+        .max()
+        .unwrap_or_default();
+    html.push_str(&format!(
+        "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>Films</th><th></th></tr>\n",
+        title, label
+    ));
+    for (name, films) in rows {
+        let width = (films * 300).checked_div(most).unwrap_or_default();
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td><div class=\"bar\" style=\"width: {}px\"></div></td></tr>\n",
+            escape_html(name),
+            films,
+            width
+        ));
+    }
+    html.push_str("</table>\n");
+}
+
+fn average_text(average: Option<f32>) -> String {
+    match average {
+        Some(average) => format!("{:.2}", average),
+        None => "none".to_string(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::movie::Movie;
+
+    use super::*;
+
+    fn movie(director: &str, year: u32, rating: Option<f32>) -> Movie {
+        Movie {
+            title: format!("Film by {}", director),
+            director: Some(director.to_string()),
+            year,
+            rating,
+        }
+    }
+
+    fn fetched(
+        collection: Collection,
+        movies: Vec<Movie>,
+        failed: usize,
+        pages: (usize, usize),
+    ) -> FetchedCollection {
+        FetchedCollection {
+            collection,
+            movies,
+            failed,
+            fetched_pages: pages.0,
+            failed_pages: pages.1,
+        }
+    }
+
+    #[test]
+    fn computes_stats_of_watched_films() {
+        let stats = ProfileStats::compute(
+            "cinefilo",
+            &[
+                fetched(
+                    Collection::Watched,
+                    vec![
+                        movie("Ridley Scott", 1979, Some(4.0)),
+                        movie("Ridley Scott, Tony Scott", 1982, Some(4.3)),
+                        movie("Fernando Meirelles", 2002, None),
+                    ],
+                    1,
+                    (1, 0),
+                ),
+                fetched(
+                    Collection::Watchlist,
+                    vec![movie("Denis Villeneuve", 2021, Some(5.0))],
+                    0,
+                    (1, 0),
+                ),
+            ],
+        );
+
+        assert_eq!(stats.collections[0].films, 3);
+        assert_eq!(stats.collections[0].failure_rate, 0.25);
+        assert_eq!(
+            stats.decades,
+            BTreeMap::from([(1970, 1), (1980, 1), (2000, 1)])
+        );
+        assert_eq!(
+            stats.top_directors,
+            vec![
+                DirectorCount {
+                    director: "Ridley Scott".to_string(),
+                    films: 2,
+                },
+                DirectorCount {
+                    director: "Fernando Meirelles".to_string(),
+                    films: 1,
+                },
+                DirectorCount {
+                    director: "Tony Scott".to_string(),
+                    films: 1,
+                },
+            ]
+        );
+        let rated: Vec<_> = stats
+            .rating_histogram
+            .iter()
+            .filter(|count| count.films > 0)
+            .map(|count| (count.rating, count.films))
+            .collect();
+        assert_eq!(rated, vec![(4.0, 1), (4.5, 1)]);
+        assert_eq!(stats.average_rating, Some(4.15));
+        assert_eq!(stats.unrated, 1);
+    }
+
+    #[test]
+    fn failure_rate_of_empty_collections_is_zero() {
+        let stats = ProfileStats::compute(
+            "cinefilo",
+            &[fetched(Collection::Watchlist, vec![], 0, (0, 0))],
+        );
+
+        assert_eq!(stats.collections[0].failure_rate, 0.0);
+        assert_eq!(stats.average_rating, None);
+    }
+
+    #[test]
+    fn failure_rate_estimates_films_of_failed_pages() {
+        let all_pages_failed = fetched(Collection::Watched, vec![], 0, (0, 2));
+        let half_pages_failed = fetched(
+            Collection::Watched,
+            vec![movie("Ridley Scott", 1979, None)],
+            1,
+            (1, 1),
+        );
+
+        assert_eq!(failure_rate(&all_pages_failed), 1.0);
+        assert_eq!(failure_rate(&half_pages_failed), 0.75);
+    }
+
+    #[test]
+    fn escapes_names_in_html() {
+        let stats = ProfileStats::compute(
+            "<b>cinéfilo</b>",
+            &[fetched(
+                Collection::Watched,
+                vec![movie("Tom & \"Jerry\"", 1940, Some(3.0))],
+                0,
+                (1, 0),
+            )],
+        );
+
+        let html = stats.to_html();
+
+        assert!(html.contains("<title>Filmow stats of &lt;b&gt;cinéfilo&lt;/b&gt;</title>"));
+        assert!(html.contains("<td>Tom &amp; &quot;Jerry&quot;</td>"));
+        assert!(!html.contains("<b>"));
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+
+use tokio::sync::watch;
+
+use crate::{
+    letterboxd_export::LetterboxdExport,
+    model::{collection::Collection, movie::Movie},
+};
+
+/// What to do with films that are both watched and on the watchlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum OverlapPolicy {
+    /// Exports them in both collections, only reporting them.
+    #[default]
+    Keep,
+    /// Drops watched films from the watchlist export.
+    PreferWatched,
+}
+
+impl FromStr for OverlapPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(OverlapPolicy::Keep),
+            "prefer-watched" => Ok(OverlapPolicy::PreferWatched),
+            _ => Err(format!(
+                "Unknown overlap policy '{}'. Expected 'keep' or 'prefer-watched'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RemovalReason {
+    /// Listed more than once in the same collection, usually because pagination shifted while
+    /// its pages were being fetched.
+    Duplicate,
+    /// On the watchlist while already watched, see `OverlapPolicy::PreferWatched`.
+    AlreadyWatched,
+}
+
+impl fmt::Display for RemovalReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemovalReason::Duplicate => write!(f, "listed more than once"),
+            RemovalReason::AlreadyWatched => write!(f, "already watched"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RemovedFilm {
+    pub collection: Collection,
+    /// Url of the film, without the query, fragment or trailing slash of the link it was
+    /// listed with.
+    pub url: String,
+    pub reason: RemovalReason,
+}
+
+/// Films left out of the export, and the ones exported both as watched and on the watchlist.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct DeduplicationReport {
+    pub removed: Vec<RemovedFilm>,
+    pub overlapping: Vec<String>,
+}
+
+impl fmt::Display for DeduplicationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Removed {} films from the export", self.removed.len())?;
+        for film in &self.removed {
+            write!(f, "\n  {} {}: {}", film.collection, film.url, film.reason)?;
+        }
+        if !self.overlapping.is_empty() {
+            write!(
+                f,
+                "\n{} films were exported both as watched and on the watchlist",
+                self.overlapping.len()
+            )?;
+            for url in &self.overlapping {
+                write!(f, "\n  {}", url)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What is known so far about the films of every collection, keyed by canonical url.
+#[derive(Debug, Default)]
+struct Films {
+    listed: HashMap<Collection, HashSet<String>>,
+    resolved: HashMap<Collection, HashSet<String>>,
+    /// Watched films whose pages couldn't be read, so they aren't exported as watched.
+    failed_watched: HashSet<String>,
+    /// Every watched film was listed, so films not listed by now aren't watched.
+    watched_listed: bool,
+    /// Every watched film was either resolved or failed.
+    watched_resolved: bool,
+}
+
+impl Films {
+    /// Whether the watchlist film at `url` is exported as watched, once that is known.
+    fn watched(&self, url: &str) -> Option<bool> {
+        let resolved = self
+            .resolved
+            .get(&Collection::Watched)
+            .is_some_and(|watched| watched.contains(url));
+        let listed = self
+            .listed
+            .get(&Collection::Watched)
+            .is_some_and(|watched| watched.contains(url));
+        if resolved {
+            Some(true)
+        } else if self.watched_resolved || self.failed_watched.contains(url) {
+            Some(false)
+        } else if listed || !self.watched_listed {
+            None
+        } else {
+            Some(false)
+        }
+    }
+}
+
+/// Decides which films discovered on listing pages are exported, keyed by canonical url.
+#[derive(Debug)]
+pub struct Deduplicator {
+    policy: OverlapPolicy,
+    films: Mutex<Films>,
+    removed: Mutex<Vec<RemovedFilm>>,
+    /// Notified whenever more is known about the watched films.
+    watched_updates: watch::Sender<()>,
+    letterboxd_export: Option<LetterboxdExport>,
+}
+
+impl Deduplicator {
+    pub fn new(policy: OverlapPolicy) -> Self {
+        Deduplicator {
+            policy,
+            films: Mutex::new(Films::default()),
+            removed: Mutex::new(vec![]),
+            watched_updates: watch::channel(()).0,
+            letterboxd_export: None,
+        }
+    }
+
+    /// Leaves out fetched films already in `export`, see `already_on_letterboxd`.
+    pub fn with_letterboxd_export(mut self, export: LetterboxdExport) -> Self {
+        self.letterboxd_export = Some(export);
+        self
+    }
+
+    /// Whether the film at canonical `url`, just listed in `collection`, should be fetched. Films
+    /// listed more than once in a collection are only fetched the first time.
+    pub fn admit(&self, collection: Collection, url: &str) -> bool {
+        let first_listed = self
+            .lock_films()
+            .listed
+            .entry(collection)
+            .or_default()
+            .insert(url.to_string());
+        if !first_listed {
+            self.remove(collection, url, RemovalReason::Duplicate);
+        }
+        first_listed
+    }
+
+    /// Whether the film at canonical `url`, whose page was just read, should be exported in
+    /// `collection`. With `OverlapPolicy::PreferWatched`, watchlist films wait only until it's
+    /// known whether that film is exported as watched.
+    pub async fn resolved(&self, collection: Collection, url: &str) -> bool {
+        if collection == Collection::Watchlist && self.policy == OverlapPolicy::PreferWatched {
+            let mut updates = self.watched_updates.subscribe();
+            let watched = loop {
+                if let Some(watched) = self.lock_films().watched(url) {
+                    break watched;
+                }
+                // The sender lives in `self`, so waiting can't fail.
+                let _ = updates.changed().await;
+            };
+            if watched {
+                self.remove(collection, url, RemovalReason::AlreadyWatched);
+                return false;
+            }
+        }
+
+        self.lock_films()
+            .resolved
+            .entry(collection)
+            .or_default()
+            .insert(url.to_string());
+        if collection == Collection::Watched {
+            self.watched_updates.send_replace(());
+        }
+        true
+    }
+
+    /// Records that the page of the film at canonical `url` couldn't be read.
+    pub fn failed(&self, collection: Collection, url: &str) {
+        if collection == Collection::Watched {
+            self.lock_films().failed_watched.insert(url.to_string());
+            self.watched_updates.send_replace(());
+        }
+    }
+
+    /// Whether the fetched `movie` is already in the Letterboxd export's counterpart of
+    /// `collection`, so it shouldn't be exported again.
+    pub fn already_on_letterboxd(&self, collection: Collection, movie: &Movie) -> bool {
+        self.letterboxd_export
+            .as_ref()
+            .is_some_and(|export| export.contains(collection, movie))
+    }
+
+    /// Marks every film of `collection` as listed, whether its listing pages could be read or not.
+    pub fn listing_complete(&self, collection: Collection) {
+        if collection == Collection::Watched {
+            self.lock_films().watched_listed = true;
+            self.watched_updates.send_replace(());
+        }
+    }
+
+    /// Marks every listed film of `collection` as resolved or failed, even the ones whose fetch
+    /// crashed.
+    pub fn resolution_complete(&self, collection: Collection) {
+        if collection == Collection::Watched {
+            let mut films = self.lock_films();
+            films.watched_listed = true;
+            films.watched_resolved = true;
+            drop(films);
+            self.watched_updates.send_replace(());
+        }
+    }
+
+    pub fn report(&self) -> DeduplicationReport {
+        let films = self.lock_films();
+        let mut overlapping: Vec<_> = match (
+            films.resolved.get(&Collection::Watched),
+            films.resolved.get(&Collection::Watchlist),
+        ) {
+            (Some(watched), Some(watchlist)) if self.policy == OverlapPolicy::Keep => {
+                watched.intersection(watchlist).cloned().collect()
+            }
+            _ => vec![],
+        };
+        overlapping.sort();
+
+        DeduplicationReport {
+            removed: self
+                .removed
+                .lock()
+                .expect("Deduplicator lock poisoned")
+                .clone(),
+            overlapping,
+        }
+    }
+
+    fn lock_films(&self) -> MutexGuard<'_, Films> {
+        self.films.lock().expect("Deduplicator lock poisoned")
+    }
+
+    fn remove(&self, collection: Collection, url: &str, reason: RemovalReason) {
+        log::info!("Skipping {} from {}: {}", url, collection, reason);
+        self.removed
+            .lock()
+            .expect("Deduplicator lock poisoned")
+            .push(RemovedFilm {
+                collection,
+                url: url.to_string(),
+                reason,
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+
+    const FILM: &str = "https://filmow.com/central-do-brasil-t44/";
+
+    async fn decided(deduplicator: &Deduplicator, collection: Collection, url: &str) -> bool {
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            deduplicator.resolved(collection, url),
+        )
+        .await
+        .expect("Film should have been decided without waiting")
+    }
+
+    #[tokio::test]
+    async fn drops_watched_films_from_watchlist_before_every_watched_film_is_listed() {
+        let deduplicator = Deduplicator::new(OverlapPolicy::PreferWatched);
+        assert!(deduplicator.admit(Collection::Watched, FILM));
+        assert!(decided(&deduplicator, Collection::Watched, FILM).await);
+
+        assert!(deduplicator.admit(Collection::Watchlist, FILM));
+        assert!(!decided(&deduplicator, Collection::Watchlist, FILM).await);
+        assert_eq!(
+            deduplicator.report().removed,
+            [RemovedFilm {
+                collection: Collection::Watchlist,
+                url: FILM.to_string(),
+                reason: RemovalReason::AlreadyWatched,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_watchlist_films_whose_watched_page_failed() {
+        let deduplicator = Deduplicator::new(OverlapPolicy::PreferWatched);
+        assert!(deduplicator.admit(Collection::Watched, FILM));
+        deduplicator.failed(Collection::Watched, FILM);
+
+        assert!(decided(&deduplicator, Collection::Watchlist, FILM).await);
+    }
+
+    #[tokio::test]
+    async fn keeps_watchlist_films_not_listed_as_watched() {
+        let deduplicator = Deduplicator::new(OverlapPolicy::PreferWatched);
+        deduplicator.listing_complete(Collection::Watched);
+
+        assert!(decided(&deduplicator, Collection::Watchlist, FILM).await);
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_watched_film_being_fetched() {
+        let deduplicator = Arc::new(Deduplicator::new(OverlapPolicy::PreferWatched));
+        assert!(deduplicator.admit(Collection::Watched, FILM));
+        deduplicator.listing_complete(Collection::Watched);
+
+        let watchlist = tokio::spawn({
+            let deduplicator = deduplicator.clone();
+            async move { deduplicator.resolved(Collection::Watchlist, FILM).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!watchlist.is_finished());
+
+        assert!(decided(&deduplicator, Collection::Watched, FILM).await);
+        assert!(!watchlist.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn stops_waiting_once_watched_films_are_done() {
+        let deduplicator = Deduplicator::new(OverlapPolicy::PreferWatched);
+        assert!(deduplicator.admit(Collection::Watched, FILM));
+        deduplicator.resolution_complete(Collection::Watched);
+
+        assert!(decided(&deduplicator, Collection::Watchlist, FILM).await);
+    }
+
+    #[tokio::test]
+    async fn reports_only_resolved_films_in_both_collections() {
+        let other = "https://filmow.com/dune-t300/";
+        let deduplicator = Deduplicator::new(OverlapPolicy::Keep);
+        for collection in [Collection::Watched, Collection::Watchlist] {
+            assert!(deduplicator.admit(collection, FILM));
+            assert!(deduplicator.admit(collection, other));
+            assert!(decided(&deduplicator, collection, FILM).await);
+        }
+        deduplicator.failed(Collection::Watched, other);
+
+        assert!(!deduplicator.admit(Collection::Watched, FILM));
+        let report = deduplicator.report();
+        assert_eq!(report.overlapping, [FILM]);
+        assert_eq!(report.removed[0].reason, RemovalReason::Duplicate);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod deduplicator;
+pub mod rating_transformer;
@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use crate::model::movie::Movie;
+
+/// Scale ratings are exported in. Filmow ratings are always extracted on the five star scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum RatingScale {
+    /// 0.5 to 5 stars, the `Rating` column of the Letterboxd importer.
+    #[default]
+    FiveStars,
+    /// 1 to 10 points, the `Rating10` column of the Letterboxd importer.
+    TenPoints,
+}
+
+impl RatingScale {
+    fn convert(&self, five_star_rating: f32) -> f32 {
+        match self {
+            RatingScale::FiveStars => five_star_rating,
+            RatingScale::TenPoints => five_star_rating * 2.0,
+        }
+    }
+}
+
+impl FromStr for RatingScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "5" | "five" | "stars" => Ok(RatingScale::FiveStars),
+            "10" | "ten" => Ok(RatingScale::TenPoints),
+            _ => Err(format!(
+                "Unknown rating scale '{}'. Expected 'five' or 'ten'",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum RoundingMode {
+    /// Keeps the converted rating as is.
+    #[default]
+    None,
+    /// Rounds to the closest multiple of 0.5.
+    Half,
+    /// Rounds to the closest integer.
+    Nearest,
+    Down,
+    Up,
+}
+
+impl RoundingMode {
+    fn round(&self, rating: f32) -> f32 {
+        match self {
+            RoundingMode::None => rating,
+            RoundingMode::Half => (rating * 2.0).round() / 2.0,
+            RoundingMode::Nearest => rating.round(),
+            RoundingMode::Down => rating.floor(),
+            RoundingMode::Up => rating.ceil(),
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(RoundingMode::None),
+            "half" => Ok(RoundingMode::Half),
+            "nearest" => Ok(RoundingMode::Nearest),
+            "down" => Ok(RoundingMode::Down),
+            "up" => Ok(RoundingMode::Up),
+            _ => Err(format!(
+                "Unknown rounding mode '{}'. Expected one of none, half, nearest, down, up",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RatingOptions {
+    pub scale: RatingScale,
+    pub rounding: RoundingMode,
+    /// Ratings below this value, expressed in the target scale, are exported as unrated.
+    pub min_rating: Option<f32>,
+    /// Exports every movie as unrated.
+    pub drop_ratings: bool,
+}
+
+/// Adapts the ratings extracted from Filmow to what the configured export target expects.
+#[derive(Debug, Clone)]
+pub struct RatingTransformer {
+    options: RatingOptions,
+}
+
+impl RatingTransformer {
+    pub fn new(options: RatingOptions) -> Self {
+        RatingTransformer { options }
+    }
+
+    pub fn apply(&self, movie: Movie) -> Movie {
+        Movie {
+            rating: movie.rating.and_then(|r| self.transform(r)),
+            ..movie
+        }
+    }
+
+    pub fn transform(&self, five_star_rating: f32) -> Option<f32> {
+        if self.options.drop_ratings {
+            return None;
+        }
+
+        let rating = self
+            .options
+            .rounding
+            .round(self.options.scale.convert(five_star_rating));
+
+        match self.options.min_rating {
+            // Ratings rounded down to zero have no equivalent on either scale.
+            _ if rating <= 0.0 => None,
+            Some(min_rating) if rating < min_rating => None,
+            _ => Some(rating),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transformer(scale: RatingScale, rounding: RoundingMode) -> RatingTransformer {
+        RatingTransformer::new(RatingOptions {
+            scale,
+            rounding,
+            ..RatingOptions::default()
+        })
+    }
+
+    #[test]
+    fn converts_to_ten_points() {
+        let transformer = transformer(RatingScale::TenPoints, RoundingMode::None);
+        assert_eq!(transformer.transform(0.5), Some(1.0));
+        assert_eq!(transformer.transform(3.5), Some(7.0));
+        assert_eq!(transformer.transform(5.0), Some(10.0));
+    }
+
+    #[test]
+    fn rounds_converted_ratings() {
+        let cases = [
+            (RoundingMode::None, 3.3, Some(3.3)),
+            (RoundingMode::Half, 3.3, Some(3.5)),
+            (RoundingMode::Half, 3.2, Some(3.0)),
+            (RoundingMode::Nearest, 3.5, Some(4.0)),
+            (RoundingMode::Nearest, 3.4, Some(3.0)),
+            (RoundingMode::Down, 3.9, Some(3.0)),
+            (RoundingMode::Up, 3.1, Some(4.0)),
+            (RoundingMode::Down, 0.5, None),
+        ];
+        for (rounding, rating, expected) in cases {
+            assert_eq!(
+                transformer(RatingScale::FiveStars, rounding).transform(rating),
+                expected,
+                "{:?} of {}",
+                rounding,
+                rating
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_ratings_below_minimum_unrated() {
+        let transformer = RatingTransformer::new(RatingOptions {
+            scale: RatingScale::TenPoints,
+            min_rating: Some(6.0),
+            ..RatingOptions::default()
+        });
+        assert_eq!(transformer.transform(2.5), None);
+        assert_eq!(transformer.transform(3.0), Some(6.0));
+    }
+
+    #[test]
+    fn drops_every_rating() {
+        let transformer = RatingTransformer::new(RatingOptions {
+            drop_ratings: true,
+            ..RatingOptions::default()
+        });
+        let movie = Movie {
+            title: "Alien".to_string(),
+            director: None,
+            year: 1979,
+            rating: Some(4.0),
+        };
+        assert_eq!(transformer.apply(movie).rating, None);
+    }
+
+    #[test]
+    fn parses_scales_and_rounding_modes() {
+        assert_eq!("ten".parse(), Ok(RatingScale::TenPoints));
+        assert_eq!("5".parse(), Ok(RatingScale::FiveStars));
+        assert!("hundred".parse::<RatingScale>().is_err());
+        assert_eq!("half".parse(), Ok(RoundingMode::Half));
+        assert!("closest".parse::<RoundingMode>().is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use csv::{ReaderBuilder, StringRecord};
+use serde::Serialize;
+
+use crate::persisters::letterboxd_csv_exporter::LetterboxdColumn;
+
+const UTF8_BOM: &str = "\u{feff}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Severity {
+    /// The importer would reject the file or import the movie wrongly.
+    Error,
+    /// The file imports, but probably not as intended.
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// Line of the file, counting the header as line 1, when the problem is on a single line.
+    pub line: Option<u64>,
+    pub column: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Problems found in an import file checked against the Letterboxd import format.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct ValidationReport {
+    pub file: PathBuf,
+    pub movies: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.count(Severity::Error) == 0
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    fn push(
+        &mut self,
+        line: Option<u64>,
+        column: Option<&str>,
+        severity: Severity,
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            column: column.map(str::to_string),
+            severity,
+            message,
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            write!(f, "{}", self.file.display())?;
+            if let Some(line) = diagnostic.line {
+                write!(f, ":{}", line)?;
+            }
+            write!(f, ": {}: ", severity)?;
+            if let Some(column) = &diagnostic.column {
+                write!(f, "[{}] ", column)?;
+            }
+            writeln!(f, "{}", diagnostic.message)?;
+        }
+
+        if self.diagnostics.is_empty() {
+            write!(
+                f,
+                "{}: no problems found in {} movies",
+                self.file.display(),
+                self.movies
+            )
+        } else {
+            write!(
+                f,
+                "{}: {} errors and {} warnings in {} movies",
+                self.file.display(),
+                self.count(Severity::Error),
+                self.count(Severity::Warning),
+                self.movies
+            )
+        }
+    }
+}
+
+/// Checks the CSV file at `path` against the columns the Letterboxd importer accepts, the same
+/// ones the `letterboxd-csv` exporter writes.
+pub fn validate_import_file(path: &Path) -> Result<ValidationReport, String> {
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Failed to read file {}. Error: {}", path.display(), e))?;
+    let mut report = ValidationReport {
+        file: path.to_path_buf(),
+        movies: 0,
+        diagnostics: vec![],
+    };
+
+    let content = match std::str::from_utf8(&bytes) {
+        Ok(content) => content.to_string(),
+        Err(e) => {
+            let line = bytes[..e.valid_up_to()]
+                .iter()
+                .filter(|b| **b == b'\n')
+                .count() as u64
+                + 1;
+            report.push(
+                Some(line),
+                None,
+                Severity::Error,
+                "File is not UTF-8 encoded, so accented titles won't match. Save it as UTF-8"
+                    .to_string(),
+            );
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+    };
+    let content = match content.strip_prefix(UTF8_BOM) {
+        Some(without_bom) => {
+            report.push(
+                Some(1),
+                None,
+                Severity::Warning,
+                "File starts with a byte order mark, which some importers read as part of the \
+                 first column name"
+                    .to_string(),
+            );
+            without_bom.to_string()
+        }
+        None => content,
+    };
+
+    let mut rdr = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let header = match rdr.headers() {
+        Ok(header) => header.clone(),
+        Err(e) => {
+            report.push(
+                Some(1),
+                None,
+                Severity::Error,
+                format!("Unreadable header: {}", e),
+            );
+            return Ok(report);
+        }
+    };
+    let columns = check_header(&header, &mut report);
+
+    let mut seen_rows: HashMap<Vec<String>, u64> = HashMap::new();
+    let mut seen_films: HashMap<(String, String), u64> = HashMap::new();
+    for record in rdr.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|position| position.line());
+                report.push(
+                    line,
+                    None,
+                    Severity::Error,
+                    format!("Unreadable row: {}", e),
+                );
+                continue;
+            }
+        };
+        let line = record.position().map(|position| position.line());
+        report.movies += 1;
+
+        if record.len() != header.len() {
+            report.push(
+                line,
+                None,
+                Severity::Error,
+                format!(
+                    "Has {} fields, but the header has {}",
+                    record.len(),
+                    header.len()
+                ),
+            );
+        }
+
+        let identified = record.iter().zip(&columns).any(|(value, column)| {
+            column.is_some_and(|column| column.identifies_film()) && !value.trim().is_empty()
+        });
+        if identifies_films(&columns) && !identified {
+            report.push(
+                line,
+                None,
+                Severity::Error,
+                format!("Has no value in any of the {} columns", identifying_names()),
+            );
+        }
+
+        for (value, column) in record.iter().zip(&columns) {
+            if let Some(column) = column {
+                if let Err(message) = column.check(value) {
+                    report.push(line, Some(column.name()), Severity::Error, message);
+                }
+            }
+        }
+
+        let line_number = line.unwrap_or_default();
+        let row: Vec<String> = record
+            .iter()
+            .map(|value| value.trim().to_string())
+            .collect();
+        if let Some(first_line) = seen_rows.get(&row) {
+            report.push(
+                line,
+                None,
+                Severity::Warning,
+                format!("Duplicates line {}", first_line),
+            );
+            continue;
+        }
+        seen_rows.insert(row, line_number);
+
+        let film = (
+            field(&record, &columns, LetterboxdColumn::Title).to_lowercase(),
+            field(&record, &columns, LetterboxdColumn::Year),
+        );
+        match seen_films.get(&film) {
+            _ if film.0.is_empty() => {}
+            Some(first_line) => report.push(
+                line,
+                None,
+                Severity::Warning,
+                format!("Same title and year as line {}", first_line),
+            ),
+            None => {
+                seen_films.insert(film, line_number);
+            }
+        }
+    }
+
+    if report.movies == 0 {
+        report.push(
+            None,
+            None,
+            Severity::Warning,
+            "File has no movies".to_string(),
+        );
+    }
+
+    Ok(report)
+}
+
+/// Reports missing, repeated and unknown columns, returning the column of every header field.
+fn check_header(
+    header: &StringRecord,
+    report: &mut ValidationReport,
+) -> Vec<Option<LetterboxdColumn>> {
+    let columns: Vec<_> = header.iter().map(LetterboxdColumn::from_name).collect();
+
+    for (name, column) in header.iter().zip(&columns) {
+        match column {
+            None => report.push(
+                Some(1),
+                Some(name),
+                Severity::Warning,
+                format!(
+                    "Unknown column, which the importer ignores. Known columns: {}",
+                    LetterboxdColumn::ALL
+                        .iter()
+                        .map(|column| column.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+            Some(column) if columns.iter().filter(|c| **c == Some(*column)).count() > 1 => report
+                .push(
+                    Some(1),
+                    Some(name),
+                    Severity::Error,
+                    "Column appears more than once".to_string(),
+                ),
+            Some(_) => {}
+        }
+    }
+
+    if !identifies_films(&columns) {
+        report.push(
+            Some(1),
+            None,
+            Severity::Error,
+            format!(
+                "No column identifies the films. Add one of {}",
+                identifying_names()
+            ),
+        );
+    }
+    if columns.contains(&Some(LetterboxdColumn::Rating))
+        && columns.contains(&Some(LetterboxdColumn::Rating10))
+    {
+        report.push(
+            Some(1),
+            None,
+            Severity::Warning,
+            "Has both Rating and Rating10 columns, only one of them is used".to_string(),
+        );
+    }
+
+    columns
+}
+
+fn identifies_films(columns: &[Option<LetterboxdColumn>]) -> bool {
+    columns
+        .iter()
+        .any(|column| column.is_some_and(|column| column.identifies_film()))
+}
+
+fn identifying_names() -> String {
+    LetterboxdColumn::ALL
+        .iter()
+        .filter(|column| column.identifies_film())
+        .map(|column| column.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn field(
+    record: &StringRecord,
+    columns: &[Option<LetterboxdColumn>],
+    column: LetterboxdColumn,
+) -> String {
+    columns
+        .iter()
+        .position(|c| *c == Some(column))
+        .and_then(|index| record.get(index))
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
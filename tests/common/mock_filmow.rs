@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Directory holding the pages served by `MockFilmow`, laid out like the urls they answer:
+/// `/usuario/joe/ja-vi/?pagina=2` is served from `usuario/joe/ja-vi/pagina-2.html` and every
+/// other path from `<path>/index.html`. Paths without a page answer 404.
+pub const FIXTURES_DIR: &str = "./tests/resources/mock_filmow";
+
+#[derive(Default)]
+struct MockState {
+    /// Path and query -> (status, remaining times it is answered with that status).
+    failures: HashMap<String, (u16, usize)>,
+    requests: Vec<String>,
+}
+
+/// In-process stand-in for filmow.com serving the fixture pages over plain HTTP.
+pub struct MockFilmow {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockFilmow {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Could not bind mock Filmow server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, server_state.clone()));
+            }
+        });
+
+        MockFilmow { base_url, state }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Answers the next `times` requests for `path` with `status` before serving the page.
+    pub fn fail_next(&self, path: &str, status: u16, times: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(path.to_string(), (status, times));
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests.len()
+    }
+
+    pub fn requests_for(&self, path: &str) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|r| r.as_str() == path)
+            .count()
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buffer[..n]),
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+    let (status, body) = respond(&path, &state);
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn respond(path: &str, state: &Mutex<MockState>) -> (u16, String) {
+    let mut state = state.lock().unwrap();
+    state.requests.push(path.to_string());
+
+    if let Some((status, remaining)) = state.failures.get_mut(path) {
+        if *remaining > 0 {
+            *remaining -= 1;
+            return (*status, format!("<html><body>{}</body></html>", status));
+        }
+    }
+
+    match std::fs::read_to_string(fixture_path(path)) {
+        Ok(body) => (200, body),
+        Err(_) => (404, "<html><body>Not found</body></html>".to_string()),
+    }
+}
+
+fn fixture_path(path: &str) -> PathBuf {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let page = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("pagina="));
+
+    let mut fixture: PathBuf = PathBuf::from(FIXTURES_DIR);
+    fixture.extend(path.split('/').filter(|s| !s.is_empty()));
+    match page {
+        Some(page) => fixture.push(format!("pagina-{}.html", page)),
+        None => fixture.push("index.html"),
+    }
+    fixture
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,44 @@
+pub mod mock_filmow;
+
+use std::{fs::File, io::Read, path::Path, sync::Arc};
+
+use filmow2letterboxd::{FixtureTransport, RunConfig};
+
+use mock_filmow::FIXTURES_DIR;
+
+/// Base url of the pages served by the `FixtureTransport`s of the tests.
+pub const FIXTURE_BASE_URL: &str = "https://filmow.example";
+
+pub fn get_file_content(file_path: &Path) -> String {
+    let mut file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(e) => panic!("Error opening file {}: {}", file_path.display(), e),
+    };
+
+    let mut content = String::new();
+    if let Err(e) = file.read_to_string(&mut content) {
+        eprintln!("Error reading the file: {}", e);
+        panic!("Failed to read file");
+    }
+
+    content
+}
+
+/// Content of a page served by `MockFilmow`, such as `duna-t300/index.html`.
+pub fn fixture_page(path: &str) -> String {
+    get_file_content(&Path::new(FIXTURES_DIR).join(path))
+}
+
+/// Url of `path` under `FIXTURE_BASE_URL`.
+pub fn fixture_url(path: &str) -> String {
+    format!("{}/{}", FIXTURE_BASE_URL, path)
+}
+
+/// Configuration of a run reading every page from `transport` and writing to `output_dir`.
+pub fn fixture_config(transport: FixtureTransport, output_dir: &Path) -> RunConfig {
+    let mut config = RunConfig::default();
+    config.output_dir = output_dir.to_path_buf();
+    config.base_url = FIXTURE_BASE_URL.to_string();
+    config.transport = Some(Arc::new(transport));
+    config
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+
+    use std::{fs, path::Path};
+
+    use filmow2letterboxd::{Collection, ExportedFilm, FilmChange};
+
+    fn film(title: &str, year: u32, rating: Option<f32>) -> ExportedFilm {
+        let mut film = ExportedFilm::default();
+        film.title = title.to_string();
+        film.year = year;
+        film.rating = rating;
+        film
+    }
+
+    fn cinefilo_export(dir: &Path) {
+        for (resource, file) in [
+            ("expected_watched_list_cinefilo.csv", "watched.csv"),
+            ("expected_watchlist_cinefilo.csv", "watchlist.csv"),
+        ] {
+            fs::copy(
+                Path::new("./tests/resources").join(resource),
+                dir.join(file),
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn lists_changes_between_exports() {
+        let old = tempfile::tempdir().unwrap();
+        cinefilo_export(old.path());
+        let new = tempfile::tempdir().unwrap();
+        fs::write(
+            new.path().join("watched.json"),
+            r#"[
+  {"title": "Alien", "director": "Ridley Scott", "year": 1979, "rating": 4.5},
+  {"title": "Cidade de Deus", "director": "Fernando Meirelles", "year": 2002, "rating": 5.0},
+  {"title": "Dune", "director": "Denis Villeneuve", "year": 2021, "rating": 4.0},
+  {"title": "Le Fabuleux Destin d'Amélie Poulain", "director": null, "year": 2001, "rating": 3.5},
+  {"title": "Her", "director": "Spike Jonze", "year": 2013, "rating": null}
+]"#,
+        )
+        .unwrap();
+        fs::write(
+            new.path().join("watchlist.json"),
+            r#"[{"title": "Central do Brasil", "director": null, "year": 1998, "rating": null}]"#,
+        )
+        .unwrap();
+
+        let diff = filmow2letterboxd::diff_exports(old.path(), new.path()).unwrap();
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                FilmChange::Added {
+                    collection: Collection::Watched,
+                    film: film("Her", 2013, None),
+                },
+                FilmChange::Removed {
+                    collection: Collection::Watched,
+                    film: film("The Godfather", 1972, Some(4.5)),
+                },
+                FilmChange::Rerated {
+                    collection: Collection::Watched,
+                    film: film("Alien", 1979, Some(4.5)),
+                    previous_rating: Some(4.0),
+                },
+                FilmChange::Rerated {
+                    collection: Collection::Watched,
+                    film: film("Le Fabuleux Destin d'Amélie Poulain", 2001, Some(3.5)),
+                    previous_rating: None,
+                },
+                FilmChange::Moved {
+                    from: Collection::Watchlist,
+                    to: Collection::Watched,
+                    film: film("Dune", 2021, Some(4.0)),
+                },
+            ]
+        );
+        assert!(diff
+            .to_string()
+            .ends_with("1 added, 1 removed, 2 re-rated and 1 moved"));
+        assert_eq!(
+            serde_json::to_value(&diff.changes[4]).unwrap(),
+            serde_json::json!({
+                "change": "moved",
+                "from": "watchlist",
+                "to": "watched",
+                "film": {"title": "Dune", "year": 2021, "rating": 4.0}
+            })
+        );
+    }
+
+    #[test]
+    fn compares_single_files_of_a_collection() {
+        let old = tempfile::tempdir().unwrap();
+        cinefilo_export(old.path());
+
+        let diff = filmow2letterboxd::diff_exports(
+            &old.path().join("watched.csv"),
+            &old.path().join("watched.csv"),
+        )
+        .unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No changes");
+
+        let unknown = filmow2letterboxd::diff_exports(
+            &old.path().join("watched.csv"),
+            Path::new("./tests/resources/expected_watched_list_cinefilo.csv"),
+        );
+        assert!(unknown
+            .unwrap_err()
+            .starts_with("Can't tell the collection"));
+    }
+}
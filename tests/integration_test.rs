@@ -1,36 +1,1274 @@
+mod common;
+
 #[cfg(test)]
 mod tests {
 
-    use std::{fs::File, io::Read};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use filmow2letterboxd::{
+        CheckStatus, Collection, ExportBuilder, ExportEvent, ExportSink, ExportStatus, Exporter,
+        FixtureTransport, HttpResponse, HttpTransport, Movie, OverlapPolicy, RatingScale,
+        RunConfig,
+    };
+
+    use crate::common::{
+        fixture_config, fixture_page, fixture_url, get_file_content, mock_filmow::MockFilmow,
+    };
+
+    fn config_for(server: &MockFilmow, output_dir: &Path) -> RunConfig {
+        let mut config = RunConfig::default();
+        config.output_dir = output_dir.to_path_buf();
+        config.base_url = server.base_url().to_string();
+        config
+    }
 
     #[tokio::test]
-    async fn outputs_azael_profile_correctly() {
-        filmow2letterboxd::run("azael".to_string()).await;
+    async fn outputs_cinefilo_profile_correctly() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+
+        filmow2letterboxd::run_with_config(
+            "cinefilo".to_string(),
+            config_for(&server, output_dir.path()),
+        )
+        .await
+        .unwrap();
 
-        let expected_watchlist_content =
-            get_file_content("./tests/resources/expected_watchlist_azael.csv");
-        let watchlist_content = get_file_content("./watchlist.csv");
+        let expected_watchlist_content = get_file_content(Path::new(
+            "./tests/resources/expected_watchlist_cinefilo.csv",
+        ));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
 
-        let expected_watched_list_content =
-            get_file_content("./tests/resources/expected_watched_list_azael.csv");
-        let watched_list_content = get_file_content("./watched.csv");
+        let expected_watched_list_content = get_file_content(Path::new(
+            "./tests/resources/expected_watched_list_cinefilo.csv",
+        ));
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
 
         assert_eq!(watchlist_content, expected_watchlist_content);
         assert_eq!(watched_list_content, expected_watched_list_content);
     }
 
-    fn get_file_content(file_path: &str) -> String {
-        let mut file = match File::open(file_path) {
-            Ok(file) => file,
-            Err(e) => panic!("Error opening expected watchlist file: {}", e),
+    #[tokio::test]
+    async fn exports_ratings_on_ten_point_scale() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.collections = vec![Collection::Watched];
+        config.rating.scale = RatingScale::TenPoints;
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watched.csv")),
+            "Title,Directors,Year,Rating10\n\
+             Alien,Ridley Scott,1979,8\n\
+             Cidade de Deus,Fernando Meirelles,2002,10\n\
+             Le Fabuleux Destin d'Amélie Poulain,Jean-Pierre Jeunet,2001,\n\
+             The Godfather,Francis Ford Coppola,1972,9\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_films_left_out_of_the_export() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let report = filmow2letterboxd::run_with_config(
+            "cinefilo".to_string(),
+            config_for(&server, output_dir.path()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.status(), ExportStatus::Partial);
+        let watched = &report.collections[0];
+        assert_eq!(watched.collection, Collection::Watched);
+        assert_eq!(watched.status, ExportStatus::Partial);
+        assert_eq!((watched.found, watched.exported), (6, 4));
+        assert_eq!(watched.failed_films.len(), 2);
+        assert_eq!(watched.files, vec![output_dir.path().join("watched.csv")]);
+        let watchlist = &report.collections[1];
+        assert_eq!(watchlist.status, ExportStatus::Complete);
+        assert_eq!((watchlist.found, watchlist.exported), (2, 2));
+        assert!(report.to_string().contains("Export partial in"));
+    }
+
+    #[tokio::test]
+    async fn reports_failed_export_when_listing_is_missing() {
+        let server = MockFilmow::start().await;
+        server.fail_next("/usuario/cinefilo/quero-ver/", 404, 1);
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.collections = vec![Collection::Watchlist];
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(report.status(), ExportStatus::Failed);
+        assert_eq!(report.collections[0].failed_pages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn returns_error_for_unknown_exporter() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = RunConfig::default();
+        config.output_dir = output_dir.path().to_path_buf();
+        config.exporters = vec!["unknown".to_string()];
+
+        assert!(
+            filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_unsorted_files_in_fetch_order() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.exporters = vec!["letterboxd-csv".to_string(), "json".to_string()];
+        config.sort_output = false;
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let expected_watched_list_content = get_file_content(Path::new(
+            "./tests/resources/expected_watched_list_cinefilo.csv",
+        ));
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
+        let mut expected_lines: Vec<_> = expected_watched_list_content.lines().collect();
+        let mut lines: Vec<_> = watched_list_content.lines().collect();
+        assert_eq!(lines[0], expected_lines[0]);
+        expected_lines.sort();
+        lines.sort();
+        assert_eq!(lines, expected_lines);
+
+        let watched_json = get_file_content(&output_dir.path().join("watched.json"));
+        let movies: Vec<serde_json::Value> = serde_json::from_str(&watched_json).unwrap();
+        assert_eq!(movies.len(), 4);
+        assert!(watched_json.starts_with("[\n  {\n    \"title\": "));
+        assert!(watched_json.ends_with("\n  }\n]"));
+    }
+
+    #[tokio::test]
+    async fn sorts_json_export_by_title() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.exporters = vec!["json".to_string()];
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let watched_json = get_file_content(&output_dir.path().join("watched.json"));
+        let movies: Vec<serde_json::Value> = serde_json::from_str(&watched_json).unwrap();
+        let titles: Vec<_> = movies
+            .iter()
+            .map(|m| m["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Alien",
+                "Cidade de Deus",
+                "Le Fabuleux Destin d'Amélie Poulain",
+                "The Godfather"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn splits_csv_files_into_numbered_parts() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.csv_chunks.max_rows = Some(3);
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        assert!(report.to_string().contains(&format!(
+            "Written to 2 files: {}, {}",
+            output_dir.path().join("watched-1.csv").display(),
+            output_dir.path().join("watched-2.csv").display()
+        )));
+        let expected_watched_list_content = get_file_content(Path::new(
+            "./tests/resources/expected_watched_list_cinefilo.csv",
+        ));
+        let lines: Vec<_> = expected_watched_list_content.lines().collect();
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watched-1.csv")),
+            format!("{}\n", lines[..4].join("\n"))
+        );
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watched-2.csv")),
+            format!("{}\n{}\n", lines[0], lines[4])
+        );
+        assert!(!output_dir.path().join("watched.csv").exists());
+
+        let expected_watchlist_content = get_file_content(Path::new(
+            "./tests/resources/expected_watchlist_cinefilo.csv",
+        ));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+        assert_eq!(watchlist_content, expected_watchlist_content);
+    }
+
+    #[tokio::test]
+    async fn splits_csv_files_by_size() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.csv_chunks.max_bytes = Some(80);
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let mut movies = vec![];
+        for part in 1.. {
+            let path = output_dir.path().join(format!("watched-{}.csv", part));
+            if !path.exists() {
+                break;
+            }
+            let content = get_file_content(&path);
+            let lines: Vec<_> = content.lines().collect();
+            assert_eq!(lines[0], "Title,Directors,Year,Rating");
+            assert!(content.len() <= 80 || lines.len() == 2);
+            movies.extend(lines[1..].iter().map(|line| line.to_string()));
+        }
+
+        let expected_watched_list_content = get_file_content(Path::new(
+            "./tests/resources/expected_watched_list_cinefilo.csv",
+        ));
+        let expected_movies: Vec<_> = expected_watched_list_content.lines().skip(1).collect();
+        assert_eq!(movies, expected_movies);
+    }
+
+    #[tokio::test]
+    async fn skips_films_already_in_letterboxd_export() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let export_dir = tempfile::tempdir().unwrap();
+        let export = export_dir.path().join("letterboxd-cinefilo.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&export).unwrap());
+        for (name, content) in [
+            (
+                "watched.csv",
+                "Date,Name,Year,Letterboxd URI\n\
+                 2023-01-02,Le fabuleux destin d\u{2019}Amélie Poulain,2001,https://boxd.it/1\n\
+                 2023-01-02,Cidade de Deus,2003,https://boxd.it/2\n\
+                 2023-01-02,Central do Brasil,1996,https://boxd.it/6\n",
+            ),
+            (
+                "ratings.csv",
+                "Date,Name,Year,Letterboxd URI,Rating\n2023-01-02,The Godfather,1972,https://boxd.it/3,4.5\n",
+            ),
+            (
+                "watchlist.csv",
+                "Date,Name,Year,Letterboxd URI\n2023-01-02,Dune,2021,https://boxd.it/4\n",
+            ),
+            (
+                "deleted/watched.csv",
+                "Date,Name,Year,Letterboxd URI\n2023-01-02,Alien,1979,https://boxd.it/5\n",
+            ),
+        ] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            std::io::Write::write_all(&mut zip, content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.letterboxd_export = Some(export);
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watched.csv")),
+            "Title,Directors,Year,Rating\nAlien,Ridley Scott,1979,4\n"
+        );
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watchlist.csv")),
+            "Title,Directors,Year,Rating\nCentral do Brasil,,1998,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn summarizes_profile_stats() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let mut config = config_for(&server, output_dir.path());
+        config.rating.scale = RatingScale::TenPoints;
+        config.rating.drop_ratings = true;
+
+        let stats = filmow2letterboxd::run_stats("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let collections: Vec<_> = stats
+            .collections
+            .iter()
+            .map(|c| (c.collection, c.films, c.failed))
+            .collect();
+        assert_eq!(
+            collections,
+            vec![(Collection::Watched, 4, 2), (Collection::Watchlist, 2, 0)]
+        );
+        assert!((stats.collections[0].failure_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            stats.decades.into_iter().collect::<Vec<_>>(),
+            vec![(1970, 2), (2000, 2)]
+        );
+        assert_eq!(
+            stats
+                .top_directors
+                .iter()
+                .map(|d| (d.director.as_str(), d.films))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Fernando Meirelles", 1),
+                ("Francis Ford Coppola", 1),
+                ("Jean-Pierre Jeunet", 1),
+                ("Ridley Scott", 1)
+            ]
+        );
+        let rated: Vec<_> = stats
+            .rating_histogram
+            .iter()
+            .filter(|count| count.films > 0)
+            .map(|count| (count.rating, count.films))
+            .collect();
+        assert_eq!(rated, vec![(4.0, 1), (4.5, 1), (5.0, 1)]);
+        assert_eq!(stats.average_rating, Some(4.5));
+        assert_eq!(stats.unrated, 1);
+        assert!(std::fs::read_dir(output_dir.path())
+            .unwrap()
+            .next()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn counts_failed_listing_pages_in_stats() {
+        let server = MockFilmow::start().await;
+        server.fail_next("/usuario/cinefilo/ja-vi/?pagina=2", 404, 1);
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.collections = vec![Collection::Watched];
+
+        let stats = filmow2letterboxd::run_stats("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let watched = &stats.collections[0];
+        assert_eq!(watched.failed_pages, 1);
+        // The failed page is taken to list as many films as the fetched one.
+        let listed = (watched.films + watched.failed) as f64 * 2.0;
+        let expected_rate = (listed - watched.films as f64) / listed;
+        assert!((watched.failure_rate - expected_rate).abs() < 1e-9);
+        assert!(stats.to_string().contains("failed listing pages: 1"));
+    }
+
+    #[tokio::test]
+    async fn fetches_movies_without_writing_files() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let fetched = ExportBuilder::new("cinefilo")
+            .base_url(server.base_url())
+            .output_dir(output_dir.path())
+            .collections([Collection::Watchlist])
+            .overlap_policy(OverlapPolicy::PreferWatched)
+            .fetch()
+            .await
+            .unwrap();
+
+        let [watchlist] = fetched.as_slice() else {
+            panic!("Expected only the watchlist, got {:?}", fetched);
         };
+        assert_eq!(watchlist.collection, Collection::Watchlist);
+        assert_eq!(watchlist.failed, 0);
+        let mut titles: Vec<_> = watchlist.movies.iter().map(|m| m.title.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["Central do Brasil", "Dune"]);
+        assert!(std::fs::read_dir(output_dir.path())
+            .unwrap()
+            .next()
+            .is_none());
+    }
+
+    /// Serves the watchlist of cinefilo, crashing when a film page is requested.
+    #[derive(Debug)]
+    struct CrashingTransport(FixtureTransport);
+
+    #[async_trait::async_trait]
+    impl HttpTransport for CrashingTransport {
+        async fn get(&self, url: &str) -> Result<HttpResponse, String> {
+            if url.contains("-t300/") {
+                panic!("Transport crashed on {}", url);
+            }
+            self.0.get(url).await
+        }
+    }
+
+    fn crashing_transport() -> CrashingTransport {
+        CrashingTransport(
+            FixtureTransport::new()
+                .with_page(
+                    &fixture_url("usuario/cinefilo/quero-ver/"),
+                    &fixture_page("usuario/cinefilo/quero-ver/index.html"),
+                )
+                .with_page(
+                    &fixture_url("central-do-brasil-t44/"),
+                    &fixture_page("central-do-brasil-t44/index.html"),
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn fails_fetch_when_fetching_crashes() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = fixture_config(FixtureTransport::new(), output_dir.path());
+
+        let fetched = ExportBuilder::new("cinefilo")
+            .with_config(config)
+            .collections([Collection::Watchlist])
+            .transport(Arc::new(crashing_transport()))
+            .fetch()
+            .await;
+
+        assert!(fetched
+            .unwrap_err()
+            .starts_with("Fetching a watchlist film crashed"));
+    }
+
+    #[tokio::test]
+    async fn fails_run_when_fetching_crashes() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = fixture_config(FixtureTransport::new(), output_dir.path());
+        config.collections = vec![Collection::Watchlist];
+        config.transport = Some(Arc::new(crashing_transport()));
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config).await;
+
+        assert!(report
+            .unwrap_err()
+            .starts_with("Fetching a watchlist film crashed"));
+    }
+
+    #[tokio::test]
+    async fn saves_recording_of_a_failed_run() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let cassette = output_dir.path().join("cassette.json");
+        let mut config = fixture_config(FixtureTransport::new(), output_dir.path());
+        config.collections = vec![Collection::Watchlist];
+        config.transport = Some(Arc::new(crashing_transport()));
+        config.record_cassette = Some(cassette.clone());
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config).await;
+
+        assert!(report.is_err());
+        assert!(get_file_content(&cassette).contains(&fixture_url("usuario/cinefilo/quero-ver/")));
+    }
+
+    #[tokio::test]
+    async fn saves_archive_index_of_a_failed_run() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let archive_dir = output_dir.path().join("archive");
+        let mut config = fixture_config(FixtureTransport::new(), output_dir.path());
+        config.collections = vec![Collection::Watchlist];
+        config.transport = Some(Arc::new(crashing_transport()));
+        config.archive_dir = Some(archive_dir.clone());
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config).await;
+
+        assert!(report.is_err());
+        assert!(get_file_content(&archive_dir.join("index.json"))
+            .contains(&fixture_url("usuario/cinefilo/quero-ver/")));
+    }
+
+    /// Exporter whose sinks fail to write any film, or only once finishing when `on_finish`.
+    #[derive(Debug)]
+    struct FailingExporter {
+        on_finish: bool,
+    }
+
+    struct FailingSink {
+        on_finish: bool,
+    }
+
+    impl Exporter for FailingExporter {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
 
-        let mut content = String::new();
-        if let Err(e) = file.read_to_string(&mut content) {
-            eprintln!("Error reading the file: {}", e);
-            panic!("Failed to read file");
+        fn open(
+            &self,
+            _collection: Collection,
+            _output_dir: &Path,
+        ) -> Result<Box<dyn ExportSink>, String> {
+            Ok(Box::new(FailingSink {
+                on_finish: self.on_finish,
+            }))
         }
+    }
+
+    impl ExportSink for FailingSink {
+        fn write(&mut self, _movie: &Movie) -> Result<(), String> {
+            match self.on_finish {
+                true => Ok(()),
+                false => Err("Disk full".to_string()),
+            }
+        }
+
+        fn finish(self: Box<Self>, _sort: bool) -> Result<Vec<PathBuf>, String> {
+            match self.on_finish {
+                true => Err("Disk full".to_string()),
+                false => Ok(vec![]),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn counts_only_films_an_exporter_wrote() {
+        let server = MockFilmow::start().await;
+
+        for on_finish in [false, true] {
+            let output_dir = tempfile::tempdir().unwrap();
+            let report = ExportBuilder::new("cinefilo")
+                .base_url(server.base_url())
+                .output_dir(output_dir.path())
+                .collections([Collection::Watchlist])
+                .exporters(Vec::<String>::new())
+                .exporter(Arc::new(FailingExporter { on_finish }))
+                .run()
+                .await
+                .unwrap();
+
+            let watchlist = &report.collections[0];
+            assert_eq!((watchlist.found, watchlist.exported), (2, 0));
+            assert_eq!(watchlist.export_errors, vec!["failing: Disk full"]);
+            assert_eq!(report.status(), ExportStatus::Failed);
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingExporter {
+        written: Arc<Mutex<Vec<(Collection, String)>>>,
+    }
 
-        return content;
+    struct RecordingSink {
+        collection: Collection,
+        written: Arc<Mutex<Vec<(Collection, String)>>>,
+    }
+
+    impl Exporter for RecordingExporter {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        fn open(
+            &self,
+            collection: Collection,
+            _output_dir: &Path,
+        ) -> Result<Box<dyn ExportSink>, String> {
+            Ok(Box::new(RecordingSink {
+                collection,
+                written: self.written.clone(),
+            }))
+        }
+    }
+
+    impl ExportSink for RecordingSink {
+        fn write(&mut self, movie: &Movie) -> Result<(), String> {
+            self.written
+                .lock()
+                .unwrap()
+                .push((self.collection, movie.title.clone()));
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>, _sort: bool) -> Result<Vec<PathBuf>, String> {
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_events_to_observers() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        ExportBuilder::new("cinefilo")
+            .base_url(server.base_url())
+            .output_dir(output_dir.path())
+            .collections([Collection::Watchlist])
+            .observer(Arc::new(sender))
+            .run()
+            .await
+            .unwrap();
+
+        let mut events = vec![];
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            events[..2],
+            [
+                ExportEvent::PageDiscovered {
+                    collection: Collection::Watchlist,
+                    page: 1
+                },
+                ExportEvent::PageFetched {
+                    collection: Collection::Watchlist,
+                    page: 1
+                },
+            ]
+        );
+        let mut resolved: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ExportEvent::FilmResolved { movie, .. } => Some(movie.title.as_str()),
+                _ => None,
+            })
+            .collect();
+        resolved.sort();
+        assert_eq!(resolved, ["Central do Brasil", "Dune"]);
+        assert_eq!(
+            events.last(),
+            Some(&ExportEvent::ExportWritten {
+                collection: Collection::Watchlist,
+                exporter: "letterboxd-csv".to_string(),
+                file: output_dir.path().join("watchlist.csv"),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn announces_pages_before_fetching_them() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        ExportBuilder::new("cinefilo")
+            .base_url(server.base_url())
+            .output_dir(output_dir.path())
+            .collections([Collection::Watched])
+            .observer(Arc::new(sender))
+            .run()
+            .await
+            .unwrap();
+
+        let mut pages = vec![];
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                ExportEvent::PageDiscovered { page, .. } => pages.push(("discovered", page)),
+                ExportEvent::PageFetched { page, .. } => pages.push(("fetched", page)),
+                _ => {}
+            }
+        }
+        assert_eq!(
+            pages,
+            [
+                ("discovered", 1),
+                ("discovered", 2),
+                ("fetched", 1),
+                ("fetched", 2)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_collections_with_custom_exporter() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let exporter = RecordingExporter::default();
+        let written = exporter.written.clone();
+
+        ExportBuilder::new("cinefilo")
+            .base_url(server.base_url())
+            .output_dir(output_dir.path())
+            .exporters(Vec::<String>::new())
+            .exporter(Arc::new(exporter))
+            .run()
+            .await
+            .unwrap();
+
+        let mut written = written.lock().unwrap().clone();
+        written.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            written,
+            vec![
+                (Collection::Watched, "Alien".to_string()),
+                (Collection::Watchlist, "Central do Brasil".to_string()),
+                (Collection::Watched, "Cidade de Deus".to_string()),
+                (Collection::Watchlist, "Dune".to_string()),
+                (
+                    Collection::Watched,
+                    "Le Fabuleux Destin d'Amélie Poulain".to_string()
+                ),
+                (Collection::Watched, "The Godfather".to_string()),
+            ]
+        );
+        assert!(std::fs::read_dir(output_dir.path())
+            .unwrap()
+            .next()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn accepts_base_url_with_trailing_slash() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = config_for(&server, output_dir.path());
+        config.base_url = format!("{}/", server.base_url());
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let expected_watchlist_content = get_file_content(Path::new(
+            "./tests/resources/expected_watchlist_cinefilo.csv",
+        ));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+
+        assert_eq!(watchlist_content, expected_watchlist_content);
+    }
+
+    #[tokio::test]
+    async fn retries_pages_while_throttled() {
+        let server = MockFilmow::start().await;
+        server.fail_next("/alien-o-oitavo-passageiro-t55/", 429, 2);
+        server.fail_next("/usuario/cinefilo/quero-ver/", 500, 1);
+        let output_dir = tempfile::tempdir().unwrap();
+
+        filmow2letterboxd::run_with_config(
+            "cinefilo".to_string(),
+            config_for(&server, output_dir.path()),
+        )
+        .await
+        .unwrap();
+
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+
+        assert!(watched_list_content.contains("Alien,Ridley Scott,1979,4"));
+        assert!(watchlist_content.contains("Dune,Denis Villeneuve,2021,"));
+        assert_eq!(server.requests_for("/alien-o-oitavo-passageiro-t55/"), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_missing_pages() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+
+        filmow2letterboxd::run_with_config(
+            "cinefilo".to_string(),
+            config_for(&server, output_dir.path()),
+        )
+        .await
+        .unwrap();
+
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
+
+        assert!(!watched_list_content.contains("Filme Removido"));
+        assert_eq!(server.requests_for("/filme-removido-t999/"), 1);
+    }
+
+    #[tokio::test]
+    async fn reads_pages_through_injected_transport() {
+        let transport = FixtureTransport::new()
+            .with_page(
+                &fixture_url("usuario/cinefilo/ja-vi/"),
+                "<html><body></body></html>",
+            )
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                &fixture_page("usuario/cinefilo/quero-ver/index.html"),
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                &fixture_page("duna-t300/index.html"),
+            )
+            .with_page(
+                &fixture_url("central-do-brasil-t44/"),
+                &fixture_page("central-do-brasil-t44/index.html"),
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = fixture_config(transport, output_dir.path());
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let expected_watchlist_content = get_file_content(Path::new(
+            "./tests/resources/expected_watchlist_cinefilo.csv",
+        ));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
+
+        assert_eq!(watchlist_content, expected_watchlist_content);
+        assert_eq!(watched_list_content, "Title,Directors,Year,Rating\n");
+    }
+
+    #[tokio::test]
+    async fn removes_duplicate_and_already_watched_films() {
+        let watched_item = |href: &str| {
+            format!(
+                r#"<li class="span2 movie_list_item" data-movie-pk="300">
+                    <a href="{}" class="cover tip-movie"></a>
+                    <span class="stars" title="Nota 4"></span>
+                </li>"#,
+                href
+            )
+        };
+        let watched_page = format!(
+            "<html><body><ul>{}{}</ul></body></html>",
+            watched_item("/duna-t300/"),
+            watched_item("/duna-t300/?origem=busca")
+        );
+        let transport = FixtureTransport::new()
+            .with_page(&fixture_url("usuario/cinefilo/ja-vi/"), &watched_page)
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                &fixture_page("usuario/cinefilo/quero-ver/index.html"),
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                &fixture_page("duna-t300/index.html"),
+            )
+            .with_page(
+                &fixture_url("central-do-brasil-t44/"),
+                &fixture_page("central-do-brasil-t44/index.html"),
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = fixture_config(transport, output_dir.path());
+        config.overlap_policy = OverlapPolicy::PreferWatched;
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+
+        assert_eq!(
+            watched_list_content,
+            "Title,Directors,Year,Rating\nDune,Denis Villeneuve,2021,4\n"
+        );
+        assert_eq!(
+            watchlist_content,
+            "Title,Directors,Year,Rating\nCentral do Brasil,,1998,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_watchlist_films_whose_watched_page_failed() {
+        let watched_page = r#"<html><body><ul>
+            <li class="span2 movie_list_item" data-movie-pk="300">
+                <a href="/duna-t300/?origem=busca" class="cover tip-movie"></a>
+                <span class="stars" title="Nota 4"></span>
+            </li>
+            </ul></body></html>"#;
+        let transport = FixtureTransport::new()
+            .with_page(&fixture_url("usuario/cinefilo/ja-vi/"), watched_page)
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                &fixture_page("usuario/cinefilo/quero-ver/index.html"),
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                &fixture_page("duna-t300/index.html"),
+            )
+            .with_page(
+                &fixture_url("central-do-brasil-t44/"),
+                &fixture_page("central-do-brasil-t44/index.html"),
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = fixture_config(transport, output_dir.path());
+        config.overlap_policy = OverlapPolicy::PreferWatched;
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(report.collections[0].failed_films.len(), 1);
+        assert!(report.deduplication.removed.is_empty());
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watchlist.csv")),
+            "Title,Directors,Year,Rating\nCentral do Brasil,,1998,\nDune,Denis Villeneuve,2021,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_ratings_it_cannot_understand() {
+        let transport = FixtureTransport::new()
+            .with_page(
+                &fixture_url("usuario/cinefilo/ja-vi/"),
+                r#"<html><body><ul>
+                <li class="span2 movie_list_item" data-movie-pk="300">
+                    <a href="/duna-t300/" class="cover tip-movie"></a>
+                    <span class="stars" title="Nota 9"></span>
+                </li>
+                </ul></body></html>"#,
+            )
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                "<html><body></body></html>",
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                &fixture_page("duna-t300/index.html"),
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let report = filmow2letterboxd::run_with_config(
+            "cinefilo".to_string(),
+            fixture_config(transport, output_dir.path()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watched.csv")),
+            "Title,Directors,Year,Rating\nDune,Denis Villeneuve,2021,\n"
+        );
+        let watched = &report.collections[0];
+        assert_eq!(watched.status, ExportStatus::Partial);
+        assert_eq!(watched.failed_ratings.len(), 1);
+        assert_eq!(watched.failed_ratings[0].url, fixture_url("duna-t300/"));
+        assert!(report.to_string().contains("Dropped rating of"));
+    }
+
+    #[tokio::test]
+    async fn follows_next_links_past_truncated_pagination() {
+        let watched_page = |film: &str, pagination: &str| {
+            format!(
+                r#"<html><body><ul>
+                <li class="span2 movie_list_item" data-movie-pk="1">
+                    <a href="/{}/" class="cover tip-movie"></a>
+                </li>
+                </ul>{}</body></html>"#,
+                film, pagination
+            )
+        };
+        let watched_url = fixture_url("usuario/cinefilo/ja-vi/");
+        let transport = FixtureTransport::new()
+            .with_page(
+                &watched_url,
+                &watched_page(
+                    "duna-t300",
+                    r#"<a href="?pagina=abc">...</a><a href="?pagina=2&ordem=titulo">2</a>"#,
+                ),
+            )
+            .with_page(
+                &format!("{}?pagina=2", watched_url),
+                &watched_page(
+                    "central-do-brasil-t44",
+                    r#"<a rel="next" href="?ordem=titulo&pagina=3">Próxima</a>"#,
+                ),
+            )
+            .with_page(
+                &format!("{}?pagina=3", watched_url),
+                &watched_page("alien-o-oitavo-passageiro-t55", ""),
+            )
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                "<html><body></body></html>",
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                &fixture_page("duna-t300/index.html"),
+            )
+            .with_page(
+                &fixture_url("central-do-brasil-t44/"),
+                &fixture_page("central-do-brasil-t44/index.html"),
+            )
+            .with_page(
+                &fixture_url("alien-o-oitavo-passageiro-t55/"),
+                &fixture_page("alien-o-oitavo-passageiro-t55/index.html"),
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = fixture_config(transport, output_dir.path());
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let watched_list_content = get_file_content(&output_dir.path().join("watched.csv"));
+
+        assert_eq!(
+            watched_list_content,
+            "Title,Directors,Year,Rating\n\
+             Alien,Ridley Scott,1979,\n\
+             Central do Brasil,,1998,\n\
+             Dune,Denis Villeneuve,2021,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn exports_from_saved_pages() {
+        let pages_dir = tempfile::tempdir().unwrap();
+        let save = |path: &str, content: &str| {
+            let file = pages_dir.path().join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, content).unwrap();
+        };
+        save(
+            "usuario/cinefilo/ja-vi/index.html",
+            "<html><body></body></html>",
+        );
+        save(
+            "usuario/cinefilo/quero-ver/index.html",
+            &fixture_page("usuario/cinefilo/quero-ver/index.html"),
+        );
+        save(
+            "Downloads/Duna - Filmow.html",
+            &fixture_page("duna-t300/index.html").replace(
+                "<head>",
+                r#"<head><link rel="canonical" href="https://filmow.com/duna-t300/">"#,
+            ),
+        );
+        save(
+            "archived/page-44.html",
+            &fixture_page("central-do-brasil-t44/index.html"),
+        );
+        save(
+            "index.json",
+            r#"{"pages": {"https://filmow.com/central-do-brasil-t44/":
+                {"file": "archived/page-44.html", "fetched_at": 0, "status": 200}}}"#,
+        );
+        let output_dir = tempfile::tempdir().unwrap();
+        let mut config = RunConfig::default();
+        config.output_dir = output_dir.path().to_path_buf();
+        config.offline_dir = Some(pages_dir.path().to_path_buf());
+
+        let report = filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(report.status(), ExportStatus::Complete);
+        assert_eq!(
+            get_file_content(&output_dir.path().join("watchlist.csv")),
+            get_file_content(Path::new(
+                "./tests/resources/expected_watchlist_cinefilo.csv"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn exports_again_from_archived_pages() {
+        let server = MockFilmow::start().await;
+        let archive_dir = tempfile::tempdir().unwrap();
+        let first_output = tempfile::tempdir().unwrap();
+        let mut archiving_config = config_for(&server, first_output.path());
+        archiving_config.archive_dir = Some(archive_dir.path().to_path_buf());
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), archiving_config)
+            .await
+            .unwrap();
+        let requests_while_archiving = server.request_count();
+        assert!(archive_dir.path().join("index.json").is_file());
+
+        let second_output = tempfile::tempdir().unwrap();
+        let mut offline_config = config_for(&server, second_output.path());
+        offline_config.offline_dir = Some(archive_dir.path().to_path_buf());
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), offline_config)
+            .await
+            .unwrap();
+
+        assert_eq!(server.request_count(), requests_while_archiving);
+        for file_name in ["watched.csv", "watchlist.csv"] {
+            assert_eq!(
+                get_file_content(&second_output.path().join(file_name)),
+                get_file_content(&first_output.path().join(file_name))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_cassette_without_accessing_filmow() {
+        let server = MockFilmow::start().await;
+        server.fail_next("/cidade-de-deus-t193/", 429, 1);
+        let recording_dir = tempfile::tempdir().unwrap();
+        let cassette = recording_dir.path().join("cassette.json");
+        let mut recording_config = config_for(&server, recording_dir.path());
+        recording_config.record_cassette = Some(cassette.clone());
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), recording_config)
+            .await
+            .unwrap();
+        let requests_while_recording = server.request_count();
+
+        let replay_dir = tempfile::tempdir().unwrap();
+        let mut replay_config = config_for(&server, replay_dir.path());
+        replay_config.replay_cassette = Some(cassette);
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), replay_config)
+            .await
+            .unwrap();
+
+        assert_eq!(server.request_count(), requests_while_recording);
+        for file_name in ["watched.csv", "watchlist.csv"] {
+            assert_eq!(
+                get_file_content(&replay_dir.path().join(file_name)),
+                get_file_content(&recording_dir.path().join(file_name))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_redesigned_pages_with_selectors_file() {
+        let transport = FixtureTransport::new()
+            .with_page(
+                &fixture_url("usuario/cinefilo/ja-vi/"),
+                "<html><body></body></html>",
+            )
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                r#"<html><body>
+                    <article class="film-card"><a class="film-link" href="/duna-t300/">Duna</a></article>
+                </body></html>"#,
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                r#"<html><body>
+                    <header><h1 class="title" data-original="Dune">Duna</h1></header>
+                    <ul class="credits"><li class="director">Denis Villeneuve</li></ul>
+                    <span class="year">2021</span>
+                </body></html>"#,
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+        let selectors_file = output_dir.path().join("selectors.json");
+        std::fs::write(
+            &selectors_file,
+            r#"{
+                "version": 1,
+                "title": ["header h1.title@data-original"],
+                "director": ["ul.credits li.director"],
+                "year": ["span.year"],
+                "watchlist_movie_link": ["article.film-card a.film-link@href"]
+            }"#,
+        )
+        .unwrap();
+        let mut config = fixture_config(transport, output_dir.path());
+        config.selectors_file = Some(selectors_file);
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+
+        assert_eq!(
+            watchlist_content,
+            "Title,Directors,Year,Rating\nDune,Denis Villeneuve,2021,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_structured_data_when_markup_changes() {
+        let transport = FixtureTransport::new()
+            .with_page(
+                &fixture_url("usuario/cinefilo/ja-vi/"),
+                "<html><body></body></html>",
+            )
+            .with_page(
+                &fixture_url("usuario/cinefilo/quero-ver/"),
+                r#"<html><body>
+                    <a data-movie-pk="300" href="/duna-t300/">Duna</a>
+                    <a data-movie-pk="44" href="/central-do-brasil-t44/">Central do Brasil</a>
+                </body></html>"#,
+            )
+            .with_page(
+                &fixture_url("duna-t300/"),
+                r#"<html><head>
+                    <script type="application/ld+json">
+                        {"@context": "https://schema.org", "@graph": [
+                            {"@type": "WebPage", "name": "Duna - Filmow"},
+                            {"@type": "Movie", "name": "Dune", "datePublished": "2021-10-21",
+                             "director": [{"@type": "Person", "name": "Denis Villeneuve"}]}
+                        ]}
+                    </script>
+                </head><body><h1>Duna</h1></body></html>"#,
+            )
+            .with_page(
+                &fixture_url("central-do-brasil-t44/"),
+                r#"<html><head>
+                    <meta property="og:title" content="Central do Brasil (1998) - Filmow">
+                    <meta property="og:type" content="video.movie">
+                </head><body><h2 class="movie-original-title">Central do Brasil</h2></body></html>"#,
+            );
+        let output_dir = tempfile::tempdir().unwrap();
+        let config = fixture_config(transport, output_dir.path());
+
+        filmow2letterboxd::run_with_config("cinefilo".to_string(), config)
+            .await
+            .unwrap();
+
+        let expected_watchlist_content = get_file_content(Path::new(
+            "./tests/resources/expected_watchlist_cinefilo.csv",
+        ));
+        let watchlist_content = get_file_content(&output_dir.path().join("watchlist.csv"));
+
+        assert_eq!(watchlist_content, expected_watchlist_content);
+    }
+
+    #[tokio::test]
+    async fn doctor_reports_pages_it_cannot_read() {
+        let server = MockFilmow::start().await;
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let report = filmow2letterboxd::run_doctor(
+            "cinefilo".to_string(),
+            10,
+            config_for(&server, output_dir.path()),
+        )
+        .await
+        .unwrap();
+
+        let status_of = |check: &str, page: &str| {
+            report
+                .checks
+                .iter()
+                .find(|c| c.check == check && c.page_url.ends_with(page))
+                .map(|c| c.status)
+        };
+        assert!(!report.is_healthy());
+        assert_eq!(
+            status_of("watched movies entries", "/usuario/cinefilo/ja-vi/"),
+            Some(CheckStatus::Ok)
+        );
+        assert_eq!(
+            status_of("film title", "/o-poderoso-chefao-t76/"),
+            Some(CheckStatus::Ok)
+        );
+        assert_eq!(
+            status_of("film director", "/central-do-brasil-t44/"),
+            Some(CheckStatus::Failed)
+        );
+        let director = report
+            .checks
+            .iter()
+            .find(|c| c.check == "film director" && c.page_url.ends_with("/central-do-brasil-t44/"))
+            .unwrap();
+        let snippet = director.snippet.as_deref().unwrap();
+        assert!(snippet.starts_with(r#"<div class="movie-title">"#));
+        assert!(!snippet.contains("<title>"));
+        assert_eq!(
+            status_of("film page", "/filme-removido-t999/"),
+            Some(CheckStatus::Failed)
+        );
     }
 }
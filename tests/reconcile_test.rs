@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod tests {
+
+    use std::{fs, path::Path};
+
+    use filmow2letterboxd::{Collection, ExportedFilm};
+
+    fn film(title: &str, year: u32, rating: Option<f32>) -> ExportedFilm {
+        let mut film = ExportedFilm::default();
+        film.title = title.to_string();
+        film.year = year;
+        film.rating = rating;
+        film
+    }
+
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        for (name, content) in files {
+            fs::write(dir.join(name), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn reports_films_letterboxd_did_not_import() {
+        let exported = tempfile::tempdir().unwrap();
+        for (resource, file) in [
+            ("expected_watched_list_cinefilo.csv", "watched.csv"),
+            ("expected_watchlist_cinefilo.csv", "watchlist.csv"),
+        ] {
+            fs::copy(
+                Path::new("./tests/resources").join(resource),
+                exported.path().join(file),
+            )
+            .unwrap();
+        }
+        let letterboxd = tempfile::tempdir().unwrap();
+        write_files(
+            letterboxd.path(),
+            &[
+                (
+                    "watched.csv",
+                    "Date,Name,Year,Letterboxd URI\n\
+                     2024-05-01,Alien,1979,https://boxd.it/1\n\
+                     2024-05-01,City of God,2002,https://boxd.it/2\n\
+                     2024-05-01,Cidade de Deus,2003,https://boxd.it/3\n\
+                     2024-05-01,The Godfather,1972,https://boxd.it/4\n\
+                     2024-05-01,Dune,2021,https://boxd.it/5\n",
+                ),
+                (
+                    "ratings.csv",
+                    "Date,Name,Year,Letterboxd URI,Rating\n\
+                     2024-05-01,Alien,1979,https://boxd.it/1,3.5\n\
+                     2024-05-01,The Godfather,1972,https://boxd.it/4,4.5\n",
+                ),
+                ("watchlist.csv", "Date,Name,Year,Letterboxd URI\n"),
+            ],
+        );
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let report =
+            filmow2letterboxd::reconcile(exported.path(), letterboxd.path(), output_dir.path())
+                .unwrap();
+
+        assert!(!report.is_complete());
+        let [watched, watchlist] = report.collections.as_slice() else {
+            panic!("Expected both collections, got {:?}", report.collections);
+        };
+        assert_eq!(watched.collection, Collection::Watched);
+        assert_eq!(watched.exported, 4);
+        assert_eq!(
+            watched.missing,
+            vec![film("Le Fabuleux Destin d'Amélie Poulain", 2001, None)]
+        );
+        assert_eq!(
+            watched
+                .rating_mismatches
+                .iter()
+                .map(|mismatch| (&mismatch.film, mismatch.letterboxd_rating))
+                .collect::<Vec<_>>(),
+            vec![(&film("Alien", 1979, Some(4.0)), Some(3.5))]
+        );
+        assert_eq!(
+            watched
+                .year_mismatches
+                .iter()
+                .map(|mismatch| (&mismatch.film, mismatch.letterboxd_year))
+                .collect::<Vec<_>>(),
+            vec![(&film("Cidade de Deus", 2002, Some(5.0)), 2003)]
+        );
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("watched-leftovers.csv")).unwrap(),
+            "Title,Directors,Year,Rating\n\
+             Le Fabuleux Destin d'Amélie Poulain,Jean-Pierre Jeunet,2001,\n"
+        );
+
+        assert_eq!(watchlist.exported, 2);
+        assert_eq!(
+            watchlist.missing,
+            vec![film("Central do Brasil", 1998, None)]
+        );
+        assert_eq!(
+            fs::read_to_string(output_dir.path().join("watchlist-leftovers.csv")).unwrap(),
+            "Title,Directors,Year,Rating\nCentral do Brasil,,1998,\n"
+        );
+    }
+
+    #[test]
+    fn reads_split_exports_rated_out_of_ten() {
+        let exported = tempfile::tempdir().unwrap();
+        write_files(
+            exported.path(),
+            &[
+                ("watched-1.csv", "Title,Year,Rating10\nAlien,1979,8\n"),
+                ("watched-2.csv", "Title,Year,Rating10\nDune,2021,9\n"),
+                ("watched-leftovers.csv", "Title,Year,Rating10\nHer,2013,7\n"),
+            ],
+        );
+        let letterboxd = tempfile::tempdir().unwrap();
+        let export = letterboxd.path().join("ratings.csv");
+        write_files(
+            letterboxd.path(),
+            &[(
+                "ratings.csv",
+                "Date,Name,Year,Letterboxd URI,Rating\n\
+                 2024-05-01,Alien,1979,https://boxd.it/1,4\n\
+                 2024-05-01,Dune,2021,https://boxd.it/5,4\n",
+            )],
+        );
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let report =
+            filmow2letterboxd::reconcile(exported.path(), &export, output_dir.path()).unwrap();
+
+        assert!(report.is_complete());
+        let [watched] = report.collections.as_slice() else {
+            panic!("Expected only watched films, got {:?}", report.collections);
+        };
+        assert_eq!(
+            watched.files,
+            vec![
+                exported.path().join("watched-1.csv"),
+                exported.path().join("watched-2.csv")
+            ]
+        );
+        assert_eq!(watched.exported, 2);
+        assert_eq!(
+            watched
+                .rating_mismatches
+                .iter()
+                .map(|mismatch| (&mismatch.film, mismatch.letterboxd_rating))
+                .collect::<Vec<_>>(),
+            vec![(&film("Dune", 2021, Some(4.5)), Some(4.0))]
+        );
+        assert_eq!(watched.leftovers, None);
+        assert!(!output_dir.path().join("watched-leftovers.csv").exists());
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+
+    use std::{fs, path::Path};
+
+    use filmow2letterboxd::{validate_import_file, Severity, ValidationReport};
+
+    fn validate(content: &[u8]) -> ValidationReport {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("watched.csv");
+        fs::write(&file, content).unwrap();
+        validate_import_file(&file).unwrap()
+    }
+
+    fn problems(report: &ValidationReport) -> Vec<(Option<u64>, Severity, Option<&str>)> {
+        report
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity, d.column.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_generated_exports() {
+        for file in [
+            "./tests/resources/expected_watched_list_cinefilo.csv",
+            "./tests/resources/expected_watchlist_cinefilo.csv",
+        ] {
+            let report = validate_import_file(Path::new(file)).unwrap();
+
+            assert!(report.is_valid());
+            assert!(report.diagnostics.is_empty(), "{}", report);
+        }
+    }
+
+    #[test]
+    fn reports_invalid_values_by_line() {
+        let report = validate(
+            "Title,Directors,Year,Rating,WatchedDate\n\
+             Alien,Ridley Scott,1979,4,2023-02-28\n\
+             Cidade de Deus,Fernando Meirelles,20O2,5,\n\
+             ,Francis Ford Coppola,1972,4.3,2023-02-30\n\
+             Dune,Denis Villeneuve,2021,\n"
+                .as_bytes(),
+        );
+
+        assert!(!report.is_valid());
+        assert_eq!(report.movies, 4);
+        assert_eq!(
+            problems(&report),
+            vec![
+                (Some(3), Severity::Error, Some("Year")),
+                (Some(4), Severity::Error, None),
+                (Some(4), Severity::Error, Some("Rating")),
+                (Some(4), Severity::Error, Some("WatchedDate")),
+                (Some(5), Severity::Error, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_header_problems() {
+        let report = validate(b"Directors,Year,Rating,Rating10,Nota\nRidley Scott,1979,4,8,\n");
+
+        assert!(!report.is_valid());
+        assert_eq!(
+            problems(&report),
+            vec![
+                (Some(1), Severity::Warning, Some("Nota")),
+                (Some(1), Severity::Error, None),
+                (Some(1), Severity::Warning, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_films_identified_without_title() {
+        let report = validate(
+            "LetterboxdURI,imdbID,tmdbID,Rating\n\
+             https://letterboxd.com/film/alien/,,,4\n\
+             ,tt0317248,,5\n\
+             ,,438631,\n\
+             ,,,3\n"
+                .as_bytes(),
+        );
+
+        assert!(!report.is_valid());
+        assert_eq!(problems(&report), vec![(Some(5), Severity::Error, None)]);
+    }
+
+    #[test]
+    fn warns_about_duplicate_rows() {
+        let report =
+            validate("Title,Year\nAlien,1979\nDune,2021\nAlien,1979\nalien,1979 \n".as_bytes());
+
+        assert!(report.is_valid());
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(|d| d.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Duplicates line 2", "Same title and year as line 2"]
+        );
+    }
+
+    #[test]
+    fn reports_encoding_problems() {
+        let report = validate(b"\xef\xbb\xbfTitle,Year\nAlien,1979\nAm\xe9lie,2001\n");
+
+        assert!(!report.is_valid());
+        assert_eq!(
+            problems(&report),
+            vec![
+                (Some(3), Severity::Error, None),
+                (Some(1), Severity::Warning, None)
+            ]
+        );
+    }
+}